@@ -0,0 +1,20 @@
+use std::env;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn main() {
+    // Static responses advertise the build time as their `Last-Modified` date. Honour
+    // `SOURCE_DATE_EPOCH` so reproducible builds produce identical headers.
+    let build_time = env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse::<u64>().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .expect("system clock is before the unix epoch")
+                .as_secs()
+        });
+
+    println!("cargo:rustc-env=HTTPBIN_BUILD_TIME={}", build_time);
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    println!("cargo:rerun-if-changed=src");
+}
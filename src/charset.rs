@@ -0,0 +1,136 @@
+use encoding_rs::{Encoding, GB18030, KOI8_R, SHIFT_JIS, WINDOWS_1252};
+
+/// A legacy character encoding that `/encoding/:charset` can transcode text into.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Charset {
+    /// True ISO-8859-1, rather than the Windows-1252 superset that browsers use for the label.
+    Latin1,
+    Windows1252,
+    Utf16Le,
+    Utf16Be,
+    Utf16LeBom,
+    Utf16BeBom,
+    ShiftJis,
+    Gb18030,
+    Koi8R,
+}
+
+impl Charset {
+    pub const ALL: [Charset; 9] = [
+        Charset::Latin1,
+        Charset::Windows1252,
+        Charset::Utf16Le,
+        Charset::Utf16Be,
+        Charset::Utf16LeBom,
+        Charset::Utf16BeBom,
+        Charset::ShiftJis,
+        Charset::Gb18030,
+        Charset::Koi8R,
+    ];
+
+    /// Looks up a charset by the path segment used to request it.
+    pub fn from_token(token: &str) -> Option<Self> {
+        Charset::ALL
+            .into_iter()
+            .find(|charset| charset.as_str().eq_ignore_ascii_case(token))
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Charset::Latin1 => "iso-8859-1",
+            Charset::Windows1252 => "windows-1252",
+            Charset::Utf16Le => "utf-16le",
+            Charset::Utf16Be => "utf-16be",
+            Charset::Utf16LeBom => "utf-16le-bom",
+            Charset::Utf16BeBom => "utf-16be-bom",
+            Charset::ShiftJis => "shift_jis",
+            Charset::Gb18030 => "gb18030",
+            Charset::Koi8R => "koi8-r",
+        }
+    }
+
+    /// The name to declare in `Content-Type` or `<meta charset>`.
+    ///
+    /// Text starting with a byte order mark is labelled plain "utf-16", because a BOM under the
+    /// UTF-16LE/BE labels is meant to be read as a zero-width no-break space (RFC 2781 3.3).
+    pub fn label(self) -> &'static str {
+        match self {
+            Charset::Utf16LeBom | Charset::Utf16BeBom => "utf-16",
+            charset => charset.as_str(),
+        }
+    }
+
+    /// Encodes `text`, replacing characters the charset cannot represent with HTML numeric
+    /// character references the way browsers do when submitting forms.
+    pub fn encode(self, text: &str) -> Vec<u8> {
+        match self {
+            Charset::Latin1 => latin1(text),
+            Charset::Windows1252 => encode_with(WINDOWS_1252, text),
+            Charset::Utf16Le => text.encode_utf16().flat_map(u16::to_le_bytes).collect(),
+            Charset::Utf16Be => text.encode_utf16().flat_map(u16::to_be_bytes).collect(),
+            Charset::Utf16LeBom => Charset::Utf16Le.encode(&format!("\u{feff}{}", text)),
+            Charset::Utf16BeBom => Charset::Utf16Be.encode(&format!("\u{feff}{}", text)),
+            Charset::ShiftJis => encode_with(SHIFT_JIS, text),
+            Charset::Gb18030 => encode_with(GB18030, text),
+            Charset::Koi8R => encode_with(KOI8_R, text),
+        }
+    }
+}
+
+fn encode_with(encoding: &'static Encoding, text: &str) -> Vec<u8> {
+    encoding.encode(text).0.into_owned()
+}
+
+// encoding_rs treats ISO-8859-1 as an alias of Windows-1252, so the real thing is done by hand.
+fn latin1(text: &str) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(text.len());
+    for char in text.chars() {
+        match u8::try_from(u32::from(char)) {
+            Ok(byte) => bytes.push(byte),
+            Err(_) => bytes.extend_from_slice(format!("&#{};", u32::from(char)).as_bytes()),
+        }
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoding_rs::{UTF_16BE, UTF_16LE};
+
+    #[test]
+    fn encodes_representable_text() {
+        assert_eq!(Charset::Latin1.encode("café"), b"caf\xe9");
+        assert_eq!(Charset::Windows1252.encode("€"), b"\x80");
+        assert_eq!(Charset::Koi8R.encode("мир"), b"\xcd\xc9\xd2");
+        assert_eq!(Charset::ShiftJis.encode("日本"), b"\x93\xfa\x96\x7b");
+    }
+
+    #[test]
+    fn replaces_unrepresentable_characters_with_references() {
+        assert_eq!(Charset::Latin1.encode("€"), b"&#8364;");
+        assert_eq!(Charset::Koi8R.encode("é"), b"&#233;");
+    }
+
+    #[test]
+    fn encodes_utf16_with_and_without_bom() {
+        let text = "a😀";
+        let little = Charset::Utf16Le.encode(text);
+        assert_eq!(UTF_16LE.decode_without_bom_handling(&little).0, text);
+        let big = Charset::Utf16Be.encode(text);
+        assert_eq!(UTF_16BE.decode_without_bom_handling(&big).0, text);
+
+        assert!(Charset::Utf16LeBom.encode(text).starts_with(b"\xff\xfe"));
+        assert!(Charset::Utf16BeBom.encode(text).starts_with(b"\xfe\xff"));
+        assert_eq!(
+            Encoding::for_bom(&Charset::Utf16BeBom.encode(text)),
+            Some((UTF_16BE, 2))
+        );
+    }
+
+    #[test]
+    fn looks_up_charsets_case_insensitively() {
+        assert_eq!(Charset::from_token("Shift_JIS"), Some(Charset::ShiftJis));
+        assert_eq!(Charset::from_token("utf-8"), None);
+    }
+}
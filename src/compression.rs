@@ -0,0 +1,205 @@
+use crate::{config, content_coding::ContentCoding, negotiation::weighted_list};
+use axum::{
+    body::{boxed, BoxBody, HttpBody, StreamBody},
+    http::{header, HeaderMap, HeaderValue, Request, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use futures_util::{stream, TryStreamExt};
+use std::{io, pin::Pin};
+use tokio_util::io::{ReaderStream, StreamReader};
+
+/// Response extension that opts a response out of negotiated compression, for bodies that are
+/// paced or flushed incrementally and would be held back by an encoder's buffering.
+#[derive(Clone, Copy)]
+pub struct Uncompressed;
+
+/// Compresses responses with the best coding the client accepts.
+///
+/// Clients can opt out of compression for a single request with `Cache-Control: no-transform`,
+/// and responses smaller than `HTTPBIN_COMPRESSION_MIN_SIZE` are always sent as-is.
+pub async fn compress_response<B>(request: Request<B>, next: Next<B>) -> Response {
+    let coding = if forbids_transform(request.headers()) {
+        None
+    } else {
+        preferred_coding(request.headers())
+    };
+    let mut response = next.run(request).await;
+
+    if !is_compressible(&response) {
+        return response;
+    }
+    add_vary(response.headers_mut());
+
+    let coding = match coding {
+        Some(coding) if response.status() != StatusCode::NOT_MODIFIED => coding,
+        _ => return response,
+    };
+
+    let headers = response.headers_mut();
+    headers.insert(
+        header::CONTENT_ENCODING,
+        HeaderValue::from_static(coding.as_str()),
+    );
+    headers.remove(header::CONTENT_LENGTH);
+    // Byte ranges refer to the unencoded representation, and a strong validator must change
+    // with the encoding, so downgrade both the way other servers do when compressing on the fly.
+    headers.remove(header::ACCEPT_RANGES);
+    if let Some(etag) = headers.get(header::ETAG) {
+        if !etag.as_bytes().starts_with(b"W/") {
+            let mut weak = b"W/".to_vec();
+            weak.extend_from_slice(etag.as_bytes());
+            if let Ok(weak) = HeaderValue::from_bytes(&weak) {
+                headers.insert(header::ETAG, weak);
+            }
+        }
+    }
+
+    // HEAD bodies are stripped by the router after this runs, so the encoder is never polled.
+    response.map(|body| encode_body(body, coding))
+}
+
+fn forbids_transform(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(header::CACHE_CONTROL)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|directive| directive.trim().eq_ignore_ascii_case("no-transform"))
+}
+
+/// Picks the coding with the highest weight in `Accept-Encoding` (RFC 9110 12.5.3), breaking ties
+/// with the server's preference. `None` means the response should not be encoded.
+fn preferred_coding(headers: &HeaderMap) -> Option<ContentCoding> {
+    let accepted = weighted_list(headers, &header::ACCEPT_ENCODING);
+    let quality_of = |coding: ContentCoding| {
+        accepted
+            .iter()
+            .find(|weighted| ContentCoding::from_token(weighted.value) == Some(coding))
+            .or_else(|| accepted.iter().find(|weighted| weighted.value == "*"))
+            .map(|weighted| weighted.quality)
+    };
+
+    let mut best: Option<(ContentCoding, u16)> = None;
+    for coding in ContentCoding::ALL {
+        match quality_of(coding) {
+            Some(quality) if quality > best.map_or(0, |(_, best)| best) => {
+                best = Some((coding, quality))
+            }
+            _ => {}
+        }
+    }
+
+    // Identity is only preferred over a coding when the client ranks it strictly higher.
+    let identity = accepted
+        .iter()
+        .find(|weighted| weighted.value.eq_ignore_ascii_case("identity"))
+        .map_or(0, |weighted| weighted.quality);
+    best.filter(|(_, quality)| *quality >= identity)
+        .map(|(coding, _)| coding)
+}
+
+fn is_compressible(response: &Response) -> bool {
+    let status = response.status();
+    if status.is_informational()
+        || status == StatusCode::NO_CONTENT
+        || status == StatusCode::PARTIAL_CONTENT
+        || response.headers().contains_key(header::CONTENT_ENCODING)
+        || response.extensions().get::<Uncompressed>().is_some()
+    {
+        return false;
+    }
+    if status == StatusCode::NOT_MODIFIED {
+        // Stands in for a response that would have been compressed.
+        return true;
+    }
+
+    let content_type = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let already_compressed = (content_type.starts_with("image/")
+        && !content_type.starts_with("image/svg+xml"))
+        || content_type.starts_with("audio/")
+        || content_type.starts_with("video/")
+        || content_type.starts_with("application/grpc")
+        || content_type.starts_with("text/event-stream");
+    if already_compressed {
+        return false;
+    }
+
+    let length = response
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())
+        .or_else(|| response.body().size_hint().exact());
+    length.is_none_or(|length| length >= config::get().compression_min_size)
+}
+
+fn add_vary(headers: &mut HeaderMap) {
+    let already_varies = headers
+        .get_all(header::VARY)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|name| {
+            let name = name.trim();
+            name == "*" || name.eq_ignore_ascii_case("accept-encoding")
+        });
+    if !already_varies {
+        headers.append(header::VARY, HeaderValue::from_static("accept-encoding"));
+    }
+}
+
+fn encode_body(mut body: BoxBody, coding: ContentCoding) -> BoxBody {
+    let data =
+        stream::poll_fn(move |cx| Pin::new(&mut body).poll_data(cx)).map_err(io::Error::other);
+    boxed(StreamBody::new(ReaderStream::new(
+        coding.encoder(StreamReader::new(data)),
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn accepting(value: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT_ENCODING, HeaderValue::from_static(value));
+        headers
+    }
+
+    #[test]
+    fn prefers_highest_weighted_coding() {
+        assert_eq!(
+            preferred_coding(&accepting("gzip;q=0.5, deflate;q=0.8, br;q=0.1")),
+            Some(ContentCoding::Deflate)
+        );
+        assert_eq!(
+            preferred_coding(&accepting("gzip, deflate, br, zstd")),
+            Some(ContentCoding::Zstd)
+        );
+    }
+
+    #[test]
+    fn applies_wildcard_to_unlisted_codings() {
+        assert_eq!(
+            preferred_coding(&accepting("zstd;q=0, br;q=0, *;q=0.5")),
+            Some(ContentCoding::Gzip)
+        );
+        assert_eq!(preferred_coding(&accepting("*;q=0")), None);
+    }
+
+    #[test]
+    fn prefers_identity_when_ranked_higher() {
+        assert_eq!(preferred_coding(&accepting("gzip;q=0.5, identity")), None);
+        assert_eq!(
+            preferred_coding(&accepting("gzip, identity")),
+            Some(ContentCoding::Gzip)
+        );
+        assert_eq!(preferred_coding(&HeaderMap::new()), None);
+        assert_eq!(preferred_coding(&accepting("compress")), None);
+    }
+}
@@ -0,0 +1,263 @@
+use crate::range;
+use axum::{
+    body::Bytes,
+    headers::{ETag, HeaderMapExt, IfModifiedSince, IfNoneMatch, LastModified},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use sha2::{Digest, Sha256};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const BUILD_TIME: &str = env!("HTTPBIN_BUILD_TIME");
+
+/// A response whose body is fixed for the lifetime of the binary, such as the bundled templates.
+///
+/// Every response carries a strong `ETag` derived from the body and a `Last-Modified` date of
+/// the build time. Successful responses additionally honour `If-None-Match`/`If-Modified-Since`
+/// with `304 Not Modified`, and single-part `Range` requests guarded by `If-Range`.
+pub struct StaticContent {
+    status: StatusCode,
+    content_type: HeaderValue,
+    body: Bytes,
+    etag: ETag,
+}
+
+impl StaticContent {
+    pub fn new(content_type: &str, body: impl Into<Bytes>) -> Self {
+        let content_type = HeaderValue::from_str(content_type).expect("invalid content type");
+        let body = body.into();
+        // The same bytes under a different media type or charset are a different representation.
+        // A NUL byte cannot occur in a header value, so it separates the two unambiguously, and
+        // SHA-256 keeps the tag stable across builds and Rust versions.
+        let digest = Sha256::new()
+            .chain_update(content_type.as_bytes())
+            .chain_update([0])
+            .chain_update(&body)
+            .finalize();
+        let etag = format!("\"{}\"", URL_SAFE_NO_PAD.encode(digest))
+            .parse()
+            .expect("base64 digests are valid entity tags");
+
+        Self {
+            status: StatusCode::OK,
+            content_type,
+            body,
+            etag,
+        }
+    }
+
+    /// Sends the content with a status other than `200 OK`. Preconditions and ranges only
+    /// apply to successful responses, so they are ignored for anything else.
+    pub fn with_status(mut self, status: StatusCode) -> Self {
+        self.status = status;
+        self
+    }
+
+    pub fn respond(self, request_headers: &HeaderMap) -> Response {
+        let last_modified = build_time();
+        let mut response = if !self.status.is_success() {
+            (self.status, self.body).into_response()
+        } else if is_not_modified(request_headers, &self.etag, last_modified) {
+            StatusCode::NOT_MODIFIED.into_response()
+        } else {
+            self.select_range(request_headers, last_modified)
+        };
+
+        let not_modified = response.status() == StatusCode::NOT_MODIFIED;
+        let headers = response.headers_mut();
+        if !not_modified {
+            headers.insert(header::CONTENT_TYPE, self.content_type);
+        }
+        if self.status.is_success() {
+            headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+        }
+        headers.typed_insert(self.etag);
+        headers.typed_insert(LastModified::from(last_modified));
+
+        response
+    }
+
+    fn select_range(&self, request_headers: &HeaderMap, last_modified: SystemTime) -> Response {
+        let selection = range::select(
+            request_headers,
+            &self.etag,
+            Some(last_modified),
+            &self.body,
+            // Multiple ranges are allowed to be answered with the full representation.
+            None,
+        );
+        let status = if selection.status == StatusCode::OK {
+            self.status
+        } else {
+            selection.status
+        };
+        let mut response = (status, selection.body).into_response();
+        if let Some(content_range) = selection.content_range {
+            response.headers_mut().insert(
+                header::CONTENT_RANGE,
+                HeaderValue::from_str(&content_range).expect("content ranges are valid headers"),
+            );
+        }
+        response
+    }
+}
+
+fn is_not_modified(request_headers: &HeaderMap, etag: &ETag, last_modified: SystemTime) -> bool {
+    // `If-Modified-Since` is only consulted without `If-None-Match` (RFC 9110 13.1.3).
+    if let Some(if_none_match) = request_headers.typed_get::<IfNoneMatch>() {
+        !if_none_match.precondition_passes(etag)
+    } else if let Some(if_modified_since) = request_headers.typed_get::<IfModifiedSince>() {
+        !if_modified_since.is_modified(last_modified)
+    } else {
+        false
+    }
+}
+
+fn build_time() -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(BUILD_TIME.parse().unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::headers::HeaderMapExt;
+
+    const CONTENT: &str = "0123456789";
+
+    fn request_headers(pairs: &[(header::HeaderName, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(name, HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    async fn body(response: Response) -> Bytes {
+        hyper::body::to_bytes(response.into_body()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn sends_validators() {
+        let response = StaticContent::new("text/plain", CONTENT).respond(&HeaderMap::new());
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let headers = response.headers();
+        assert!(headers.typed_get::<ETag>().is_some());
+        assert!(headers.typed_get::<LastModified>().is_some());
+        assert_eq!(
+            headers.get(header::ACCEPT_RANGES),
+            Some(&HeaderValue::from_static("bytes"))
+        );
+        assert_eq!(body(response).await, CONTENT);
+    }
+
+    #[tokio::test]
+    async fn not_modified_when_etag_matches() {
+        let etag = StaticContent::new("text/plain", CONTENT).etag;
+        let headers =
+            request_headers(&[(header::IF_NONE_MATCH, &format!("W/{}", etag_str(&etag)))]);
+
+        let response = StaticContent::new("text/plain", CONTENT).respond(&headers);
+
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert!(response.headers().get(header::CONTENT_TYPE).is_none());
+        assert!(body(response).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn not_modified_since_build_time() {
+        let headers =
+            request_headers(&[(header::IF_MODIFIED_SINCE, "Fri, 31 Dec 9999 23:59:59 GMT")]);
+
+        let response = StaticContent::new("text/plain", CONTENT).respond(&headers);
+
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    }
+
+    #[tokio::test]
+    async fn if_none_match_takes_precedence_over_if_modified_since() {
+        let headers = request_headers(&[
+            (header::IF_NONE_MATCH, "\"stale\""),
+            (header::IF_MODIFIED_SINCE, "Fri, 31 Dec 9999 23:59:59 GMT"),
+        ]);
+
+        let response = StaticContent::new("text/plain", CONTENT).respond(&headers);
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn serves_a_single_range() {
+        let headers = request_headers(&[(header::RANGE, "bytes=2-4")]);
+
+        let response = StaticContent::new("text/plain", CONTENT).respond(&headers);
+
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(
+            response.headers().get(header::CONTENT_RANGE),
+            Some(&HeaderValue::from_static("bytes 2-4/10"))
+        );
+        assert_eq!(body(response).await, "234");
+    }
+
+    #[tokio::test]
+    async fn serves_full_content_when_if_range_does_not_match() {
+        let headers = request_headers(&[
+            (header::RANGE, "bytes=2-4"),
+            (header::IF_RANGE, "\"stale\""),
+        ]);
+
+        let response = StaticContent::new("text/plain", CONTENT).respond(&headers);
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body(response).await, CONTENT);
+    }
+
+    #[tokio::test]
+    async fn serves_range_when_if_range_matches() {
+        let etag = StaticContent::new("text/plain", CONTENT).etag;
+        let headers = request_headers(&[
+            (header::RANGE, "bytes=-3"),
+            (header::IF_RANGE, &etag_str(&etag)),
+        ]);
+
+        let response = StaticContent::new("text/plain", CONTENT).respond(&headers);
+
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(body(response).await, "789");
+    }
+
+    #[tokio::test]
+    async fn rejects_unsatisfiable_ranges() {
+        let headers = request_headers(&[(header::RANGE, "bytes=20-")]);
+
+        let response = StaticContent::new("text/plain", CONTENT).respond(&headers);
+
+        assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(
+            response.headers().get(header::CONTENT_RANGE),
+            Some(&HeaderValue::from_static("bytes */10"))
+        );
+    }
+
+    #[tokio::test]
+    async fn ignores_preconditions_for_unsuccessful_responses() {
+        let etag = StaticContent::new("text/plain", CONTENT).etag;
+        let headers = request_headers(&[(header::IF_NONE_MATCH, &etag_str(&etag))]);
+
+        let response = StaticContent::new("text/plain", CONTENT)
+            .with_status(StatusCode::NOT_FOUND)
+            .respond(&headers);
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert!(response.headers().typed_get::<ETag>().is_some());
+        assert_eq!(body(response).await, CONTENT);
+    }
+
+    fn etag_str(etag: &ETag) -> String {
+        let mut headers = HeaderMap::new();
+        headers.typed_insert(etag.clone());
+        headers[header::ETAG].to_str().unwrap().to_owned()
+    }
+}
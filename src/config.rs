@@ -0,0 +1,127 @@
+use std::env;
+use std::fmt::Display;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// The largest `HTTPBIN_MAX_DELAY` accepted, in seconds: a day.
+const MAX_DELAY_LIMIT: f64 = 24.0 * 60.0 * 60.0;
+
+/// Server-wide settings, read once from `HTTPBIN_*` environment variables.
+pub struct Config {
+    /// Responses with a known length below this many bytes are never compressed.
+    pub compression_min_size: u64,
+    /// Request bodies may not expand beyond this many bytes when their `Content-Encoding` is
+    /// removed.
+    pub max_decompressed_size: u64,
+    /// The largest decoded size that `/compressed/bomb/{coding}` will produce.
+    pub max_bomb_size: u64,
+    /// The largest body `/bytes/{n}` will generate, which is buffered in memory.
+    pub max_bytes: u64,
+    /// The largest body `/stream-bytes/{n}` or `/throttle/{n}` will generate, which is streamed.
+    pub max_stream_bytes: u64,
+    /// The longest delay, in seconds, that a client may ask an endpoint to wait.
+    pub max_delay: f64,
+}
+
+/// Looks up a setting by its variable name.
+type Lookup<'a> = &'a dyn Fn(&str) -> Option<String>;
+
+impl Config {
+    fn from_env() -> Self {
+        Self::from_lookup(&|name| env::var(name).ok())
+    }
+
+    fn from_lookup(lookup: Lookup) -> Self {
+        Self {
+            compression_min_size: var_or(lookup, "HTTPBIN_COMPRESSION_MIN_SIZE", 32),
+            max_decompressed_size: var_or(
+                lookup,
+                "HTTPBIN_MAX_DECOMPRESSED_SIZE",
+                10 * 1024 * 1024,
+            ),
+            max_bomb_size: var_or(lookup, "HTTPBIN_MAX_BOMB_SIZE", 100 * 1024 * 1024),
+            max_bytes: var_or(lookup, "HTTPBIN_MAX_BYTES", 10 * 1024 * 1024),
+            max_stream_bytes: var_or(lookup, "HTTPBIN_MAX_STREAM_BYTES", 10 * 1024 * 1024 * 1024),
+            max_delay: var_within(lookup, "HTTPBIN_MAX_DELAY", 10.0, 0.0..=MAX_DELAY_LIMIT),
+        }
+    }
+}
+
+pub fn get() -> &'static Config {
+    static CONFIG: OnceLock<Config> = OnceLock::new();
+    CONFIG.get_or_init(Config::from_env)
+}
+
+/// Checks the query parameter `name`, a duration in seconds requested by the client, against
+/// `HTTPBIN_MAX_DELAY`.
+pub fn parse_duration(name: &str, seconds: f64) -> Result<Duration, String> {
+    let max = get().max_delay;
+    if !(0.0..=max).contains(&seconds) {
+        return Err(format!("{} must be between 0 and {} seconds", name, max));
+    }
+    Ok(Duration::from_secs_f64(seconds))
+}
+
+fn var_or<T: FromStr>(lookup: Lookup, name: &str, default: T) -> T {
+    match lookup(name) {
+        Some(value) => value.parse().unwrap_or_else(|_| {
+            tracing::warn!("ignoring invalid value {:?} for {}", value, name);
+            default
+        }),
+        None => default,
+    }
+}
+
+/// Like [`var_or`], but also falls back to `default` when the value is outside `range`, which
+/// rules out NaN and infinities for floats.
+fn var_within<T: FromStr + PartialOrd + Display + Copy>(
+    lookup: Lookup,
+    name: &str,
+    default: T,
+    range: RangeInclusive<T>,
+) -> T {
+    let value = var_or(lookup, name, default);
+    if range.contains(&value) {
+        return value;
+    }
+    tracing::warn!(
+        "ignoring {} for {}, which must be between {} and {}",
+        value,
+        name,
+        range.start(),
+        range.end()
+    );
+    default
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_delays_out_of_range() {
+        for (value, expected) in [
+            ("2.5", 2.5),
+            ("86400", 86400.0),
+            ("inf", 10.0),
+            ("NaN", 10.0),
+            ("1e300", 10.0),
+            ("-1", 10.0),
+            ("soon", 10.0),
+        ] {
+            let config = Config::from_lookup(&|name| {
+                (name == "HTTPBIN_MAX_DELAY").then(|| value.to_owned())
+            });
+            assert_eq!(config.max_delay, expected, "{}", value);
+        }
+    }
+
+    #[test]
+    fn defaults_unset_variables() {
+        let config = Config::from_lookup(&|_| None);
+        assert_eq!(config.max_bytes, 10 * 1024 * 1024);
+        assert_eq!(config.max_delay, 10.0);
+    }
+}
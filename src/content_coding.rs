@@ -0,0 +1,127 @@
+use async_compression::{
+    tokio::bufread::{
+        BrotliDecoder, BrotliEncoder, GzipDecoder, GzipEncoder, ZlibDecoder, ZlibEncoder,
+        ZstdDecoder, ZstdEncoder,
+    },
+    Level,
+};
+use std::{io, pin::Pin};
+use tokio::io::{AsyncBufRead, AsyncRead, AsyncReadExt};
+
+/// Why a body could not be decoded.
+#[derive(Debug)]
+pub enum DecodeError {
+    /// The decoded body would exceed the allowed size.
+    TooLarge,
+    /// The body is not valid for its coding.
+    Corrupt(io::Error),
+}
+
+/// A `Content-Encoding` that httpbin-rs can produce.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ContentCoding {
+    Gzip,
+    /// The HTTP "deflate" coding, which is a zlib stream rather than raw DEFLATE (RFC 9110 8.4.1.2).
+    Deflate,
+    Brotli,
+    Zstd,
+}
+
+impl ContentCoding {
+    /// Every supported coding, from most to least preferred by the server.
+    pub const ALL: [ContentCoding; 4] = [
+        ContentCoding::Zstd,
+        ContentCoding::Brotli,
+        ContentCoding::Gzip,
+        ContentCoding::Deflate,
+    ];
+
+    pub fn from_token(token: &str) -> Option<Self> {
+        ContentCoding::ALL
+            .into_iter()
+            .find(|coding| coding.as_str().eq_ignore_ascii_case(token))
+            .or_else(|| {
+                token
+                    .eq_ignore_ascii_case("x-gzip")
+                    .then_some(ContentCoding::Gzip)
+            })
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ContentCoding::Gzip => "gzip",
+            ContentCoding::Deflate => "deflate",
+            ContentCoding::Brotli => "br",
+            ContentCoding::Zstd => "zstd",
+        }
+    }
+
+    /// Wraps `reader` so that reading from it yields the encoded stream.
+    pub fn encoder<'a>(
+        self,
+        reader: impl AsyncBufRead + Send + 'a,
+    ) -> Pin<Box<dyn AsyncRead + Send + 'a>> {
+        self.encoder_with_level(reader, Level::Default)
+    }
+
+    pub fn encoder_with_level<'a>(
+        self,
+        reader: impl AsyncBufRead + Send + 'a,
+        level: Level,
+    ) -> Pin<Box<dyn AsyncRead + Send + 'a>> {
+        match self {
+            ContentCoding::Gzip => Box::pin(GzipEncoder::with_quality(reader, level)),
+            ContentCoding::Deflate => Box::pin(ZlibEncoder::with_quality(reader, level)),
+            ContentCoding::Brotli => {
+                // Brotli's own default (11) is far too slow to compress responses on the fly.
+                let level = match level {
+                    Level::Default => Level::Precise(4),
+                    level => level,
+                };
+                Box::pin(BrotliEncoder::with_quality(reader, level))
+            }
+            ContentCoding::Zstd => Box::pin(ZstdEncoder::with_quality(reader, level)),
+        }
+    }
+
+    /// Wraps `reader` so that reading from it yields the decoded stream.
+    pub fn decoder<'a>(
+        self,
+        reader: impl AsyncBufRead + Send + 'a,
+    ) -> Pin<Box<dyn AsyncRead + Send + 'a>> {
+        match self {
+            ContentCoding::Gzip => {
+                let mut decoder = GzipDecoder::new(reader);
+                decoder.multiple_members(true);
+                Box::pin(decoder)
+            }
+            ContentCoding::Deflate => Box::pin(ZlibDecoder::new(reader)),
+            ContentCoding::Brotli => Box::pin(BrotliDecoder::new(reader)),
+            ContentCoding::Zstd => Box::pin(ZstdDecoder::new(reader)),
+        }
+    }
+
+    /// Decodes `data`, giving up as soon as the output grows past `limit` bytes.
+    pub async fn decode(self, data: &[u8], limit: u64) -> Result<Vec<u8>, DecodeError> {
+        let mut decoded = vec![];
+        self.decoder(data)
+            .take(limit.saturating_add(1))
+            .read_to_end(&mut decoded)
+            .await
+            .map_err(DecodeError::Corrupt)?;
+        if decoded.len() as u64 > limit {
+            return Err(DecodeError::TooLarge);
+        }
+        Ok(decoded)
+    }
+
+    pub async fn encode(self, data: &[u8]) -> Vec<u8> {
+        let mut encoded = vec![];
+        // Reading from an in-memory slice cannot fail.
+        self.encoder(data)
+            .read_to_end(&mut encoded)
+            .await
+            .expect("encoding an in-memory buffer");
+        encoded
+    }
+}
@@ -0,0 +1,381 @@
+use crate::{
+    config,
+    content_coding::{ContentCoding, DecodeError},
+    format::Format,
+};
+use axum::{
+    async_trait,
+    body::{Bytes, HttpBody},
+    extract::{ConnectInfo, FromRequest, FromRequestParts},
+    http::{header, request::Parts, HeaderMap, Request, StatusCode},
+    response::{IntoResponse, Response},
+    BoxError, Json,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use futures_util::stream;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+
+/// The parts of a request that httpbin reflects back in its JSON responses.
+#[derive(Serialize)]
+pub struct RequestEcho {
+    pub args: Map<String, Value>,
+    pub headers: Map<String, Value>,
+    pub method: String,
+    pub origin: String,
+    pub url: String,
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for RequestEcho
+where
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let ConnectInfo(addr) = ConnectInfo::<SocketAddr>::from_request_parts(parts, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+
+        let query = parts.uri.query().unwrap_or_default();
+        let args = serde_urlencoded::from_str::<Vec<(String, String)>>(query).unwrap_or_default();
+
+        Ok(Self {
+            args: pairs_to_json(args),
+            headers: headers_to_json(&parts.headers),
+            method: parts.method.to_string(),
+            origin: addr.ip().to_string(),
+            url: request_url(parts),
+        })
+    }
+}
+
+/// The request body as httpbin reflects it, after undoing any `Content-Encoding`.
+///
+/// Form bodies fill `form` (and `files` for multipart uploads), anything else is returned as
+/// text in `data`, and `json` holds the parsed body when it is valid JSON. XML, YAML,
+/// MessagePack, CBOR and NDJSON bodies are also parsed into `parsed`, or described in
+/// `parse_error` when they are malformed.
+#[derive(Serialize)]
+pub struct BodyEcho {
+    pub data: String,
+    pub files: Map<String, Value>,
+    pub form: Map<String, Value>,
+    pub json: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parsed: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parse_error: Option<ParseError>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_encoding: Option<DecodedBody>,
+}
+
+/// Everything httpbin reflects about a request, including its body.
+#[derive(Serialize)]
+pub struct Echo {
+    #[serde(flatten)]
+    pub request: RequestEcho,
+    #[serde(flatten)]
+    pub body: BodyEcho,
+}
+
+/// Why a body in one of the structured formats could not be parsed.
+#[derive(Serialize)]
+pub struct ParseError {
+    pub format: &'static str,
+    pub message: String,
+}
+
+/// How an encoded request body was unpacked.
+#[derive(Serialize)]
+pub struct DecodedBody {
+    /// Codings in the order they were applied by the client.
+    pub codings: Vec<&'static str>,
+    pub encoded_size: usize,
+    pub decoded_size: usize,
+}
+
+#[async_trait]
+impl<S, B> FromRequest<S, B> for BodyEcho
+where
+    B: HttpBody + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<BoxError>,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request(request: Request<B>, state: &S) -> Result<Self, Self::Rejection> {
+        let headers = request.headers().clone();
+        let body = Bytes::from_request(request, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+
+        let (body, content_encoding) = decode_body(&headers, body).await?;
+        let content_type = headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default();
+        let mime = content_type.parse::<mime::Mime>().ok();
+
+        let mut echo = Self {
+            data: String::new(),
+            files: Map::new(),
+            form: Map::new(),
+            json: serde_json::from_slice(&body).unwrap_or(Value::Null),
+            parsed: None,
+            parse_error: None,
+            content_encoding,
+        };
+        if let Some((format, parsed)) = mime.as_ref().and_then(|mime| parse_structured(mime, &body))
+        {
+            match parsed {
+                Ok(parsed) => echo.parsed = Some(parsed),
+                Err(message) => {
+                    echo.parsed = Some(Value::Null);
+                    echo.parse_error = Some(ParseError { format, message });
+                }
+            }
+        }
+        match mime.as_ref().map(|mime| mime.essence_str()) {
+            Some("application/x-www-form-urlencoded") => {
+                let pairs = serde_urlencoded::from_bytes::<Vec<(String, String)>>(&body)
+                    .map_err(|_| bad_request("Invalid form body"))?;
+                echo.form = pairs_to_json(pairs);
+            }
+            Some("multipart/form-data") => {
+                let (form, files) = parse_multipart(content_type, body).await?;
+                echo.form = form;
+                echo.files = files;
+            }
+            _ => echo.data = body_to_string(&body, content_type),
+        }
+
+        Ok(echo)
+    }
+}
+
+async fn decode_body(
+    headers: &HeaderMap,
+    body: Bytes,
+) -> Result<(Bytes, Option<DecodedBody>), Response> {
+    let mut codings = vec![];
+    for token in headers
+        .get_all(header::CONTENT_ENCODING)
+        .iter()
+        .flat_map(|value| value.to_str().unwrap_or_default().split(','))
+        .map(str::trim)
+        .filter(|token| !token.is_empty() && !token.eq_ignore_ascii_case("identity"))
+    {
+        match ContentCoding::from_token(token) {
+            Some(coding) => codings.push(coding),
+            None => return Err(unsupported_encoding(token)),
+        }
+    }
+    if codings.is_empty() {
+        return Ok((body, None));
+    }
+
+    let encoded_size = body.len();
+    let limit = config::get().max_decompressed_size;
+    let mut decoded = body;
+    // Codings are listed in the order they were applied, so they are removed in reverse.
+    for coding in codings.iter().rev() {
+        decoded = match coding.decode(&decoded, limit).await {
+            Ok(decoded) => decoded.into(),
+            Err(DecodeError::TooLarge) => {
+                return Err(payload_too_large(&format!(
+                    "Decompressed body exceeds {} bytes",
+                    limit
+                )))
+            }
+            Err(DecodeError::Corrupt(error)) => {
+                return Err(bad_request(&format!(
+                    "Invalid {} body: {}",
+                    coding.as_str(),
+                    error
+                )))
+            }
+        };
+    }
+
+    let decoded_size = decoded.len();
+    Ok((
+        decoded,
+        Some(DecodedBody {
+            codings: codings.iter().map(|coding| coding.as_str()).collect(),
+            encoded_size,
+            decoded_size,
+        }),
+    ))
+}
+
+/// Parses bodies whose media type names one of the structured formats, returning the format's
+/// name along with the result. Structured syntax suffixes such as `+xml` count too.
+fn parse_structured(
+    mime: &mime::Mime,
+    body: &[u8],
+) -> Option<(&'static str, Result<Value, String>)> {
+    let suffix = mime.suffix().map(|suffix| suffix.as_str());
+    let parsed = match (mime.essence_str(), suffix) {
+        ("application/xml" | "text/xml", _) | (_, Some("xml")) => ("xml", Format::Xml.parse(body)),
+        ("application/yaml" | "application/x-yaml" | "text/yaml", _) | (_, Some("yaml")) => {
+            ("yaml", Format::Yaml.parse(body))
+        }
+        ("application/msgpack" | "application/x-msgpack" | "application/vnd.msgpack", _) => {
+            ("msgpack", Format::MessagePack.parse(body))
+        }
+        ("application/cbor", _) | (_, Some("cbor")) => ("cbor", Format::Cbor.parse(body)),
+        ("application/x-ndjson" | "application/ndjson" | "application/jsonl", _) => {
+            ("ndjson", parse_ndjson(body))
+        }
+        _ => return None,
+    };
+    Some(parsed)
+}
+
+/// Parses newline-delimited JSON into an array, skipping blank lines.
+fn parse_ndjson(body: &[u8]) -> Result<Value, String> {
+    body.split(|byte| *byte == b'\n')
+        .enumerate()
+        .filter(|(_, line)| !line.trim_ascii().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_slice(line).map_err(|error| format!("line {}: {}", index + 1, error))
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(Value::Array)
+}
+
+fn unsupported_encoding(token: &str) -> Response {
+    let supported: Vec<_> = ContentCoding::ALL
+        .iter()
+        .map(|coding| coding.as_str())
+        .collect();
+    (
+        StatusCode::UNSUPPORTED_MEDIA_TYPE,
+        // RFC 9110 15.5.16 suggests advertising the codings that would have been accepted.
+        [(header::ACCEPT_ENCODING, supported.join(", "))],
+        Json(json!({
+            "message": format!("Unsupported Content-Encoding \"{}\".", token),
+            "accept_encoding": supported,
+        })),
+    )
+        .into_response()
+}
+
+async fn parse_multipart(
+    content_type: &str,
+    body: Bytes,
+) -> Result<(Map<String, Value>, Map<String, Value>), Response> {
+    let invalid = |_| bad_request("Invalid multipart body");
+    let boundary = multer::parse_boundary(content_type).map_err(invalid)?;
+    let mut multipart = multer::Multipart::new(
+        stream::once(async move { Ok::<_, Infallible>(body) }),
+        boundary,
+    );
+
+    let mut form = vec![];
+    let mut files = vec![];
+    while let Some(field) = multipart.next_field().await.map_err(invalid)? {
+        let name = field.name().unwrap_or_default().to_owned();
+        let is_file = field.file_name().is_some();
+        let content_type = field
+            .content_type()
+            .map(|mime| mime.to_string())
+            .unwrap_or_default();
+        let content = field.bytes().await.map_err(invalid)?;
+        if is_file {
+            files.push((name, body_to_string(&content, &content_type)));
+        } else {
+            form.push((name, String::from_utf8_lossy(&content).into_owned()));
+        }
+    }
+
+    Ok((pairs_to_json(form), pairs_to_json(files)))
+}
+
+/// A `400 Bad Request` explaining the problem in plain text.
+pub fn bad_request(message: &str) -> Response {
+    (
+        StatusCode::BAD_REQUEST,
+        [(header::CONTENT_TYPE, mime::TEXT_PLAIN.as_ref())],
+        message.to_owned(),
+    )
+        .into_response()
+}
+
+/// A `413 Payload Too Large` explaining the limit in plain text.
+pub fn payload_too_large(message: &str) -> Response {
+    (
+        StatusCode::PAYLOAD_TOO_LARGE,
+        [(header::CONTENT_TYPE, mime::TEXT_PLAIN.as_ref())],
+        message.to_owned(),
+    )
+        .into_response()
+}
+
+/// Returns text bodies as-is and anything else as a base64 `data:` URL, like httpbin.
+fn body_to_string(body: &[u8], content_type: &str) -> String {
+    match std::str::from_utf8(body) {
+        Ok(text) => text.to_owned(),
+        Err(_) => {
+            let content_type = if content_type.is_empty() {
+                mime::APPLICATION_OCTET_STREAM.as_ref()
+            } else {
+                content_type
+            };
+            format!("data:{};base64,{}", content_type, STANDARD.encode(body))
+        }
+    }
+}
+
+fn request_url(parts: &Parts) -> String {
+    match request_origin(&parts.headers) {
+        Some(origin) => format!("{}{}", origin, parts.uri),
+        None => parts.uri.to_string(),
+    }
+}
+
+/// The scheme and authority the client addressed, from `Host` and `X-Forwarded-Proto`.
+pub fn request_origin(headers: &HeaderMap) -> Option<String> {
+    let scheme = headers
+        .get("x-forwarded-proto")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("http");
+    headers
+        .get(header::HOST)
+        .and_then(|value| value.to_str().ok())
+        .map(|host| format!("{}://{}", scheme, host))
+}
+
+/// Collects key/value pairs into a JSON object, turning repeated keys into arrays.
+fn pairs_to_json(pairs: Vec<(String, String)>) -> Map<String, Value> {
+    let mut json = Map::new();
+    for (key, value) in pairs {
+        match json.get_mut(&key) {
+            Some(Value::Array(values)) => values.push(Value::String(value)),
+            Some(existing) => *existing = json!([existing.take(), value]),
+            None => {
+                json.insert(key, Value::String(value));
+            }
+        }
+    }
+    json
+}
+
+/// Converts request headers to a JSON object, joining repeated headers with commas.
+pub fn headers_to_json(headers: &HeaderMap) -> Map<String, Value> {
+    let mut json = Map::new();
+    for name in headers.keys() {
+        let values: Vec<_> = headers
+            .get_all(name)
+            .iter()
+            .map(|value| String::from_utf8_lossy(value.as_bytes()))
+            .collect();
+        json.insert(name.as_str().into(), Value::String(values.join(", ")));
+    }
+    json
+}
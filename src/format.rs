@@ -0,0 +1,602 @@
+use crate::negotiation::media_ranges;
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{header, request::Parts, StatusCode},
+    response::{IntoResponse, Response},
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{
+    de::{self, MapAccess, SeqAccess, Visitor},
+    Deserialize, Deserializer,
+};
+use serde_json::{Map, Number, Value};
+use std::fmt;
+
+/// A serialization format that JSON-shaped response data can be rendered in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    /// JSON indented for reading, which shares its media type with [`Format::Json`].
+    PrettyJson,
+    Xml,
+    Yaml,
+    MessagePack,
+    Cbor,
+    /// One `key: value` line per leaf, with nested keys joined by dots.
+    Text,
+}
+
+impl Format {
+    /// Every format `/negotiate` offers, with the media types it is served as, the first being
+    /// the canonical one.
+    pub const MEDIA_TYPES: [(&'static str, Format); 8] = [
+        ("application/json", Format::Json),
+        ("application/xml", Format::Xml),
+        ("text/xml", Format::Xml),
+        ("application/yaml", Format::Yaml),
+        ("application/x-yaml", Format::Yaml),
+        ("application/msgpack", Format::MessagePack),
+        ("application/x-msgpack", Format::MessagePack),
+        ("application/cbor", Format::Cbor),
+    ];
+
+    /// Names accepted by the `format` query parameter.
+    pub const NAMES: [(&'static str, Format); 7] = [
+        ("json", Format::Json),
+        ("pretty", Format::PrettyJson),
+        ("xml", Format::Xml),
+        ("yaml", Format::Yaml),
+        ("msgpack", Format::MessagePack),
+        ("cbor", Format::Cbor),
+        ("text", Format::Text),
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Format::NAMES
+            .into_iter()
+            .find(|(candidate, _)| candidate.eq_ignore_ascii_case(name))
+            .map(|(_, format)| format)
+    }
+
+    pub fn media_type(self) -> &'static str {
+        match self {
+            Format::PrettyJson => "application/json",
+            Format::Text => "text/plain",
+            format => Format::MEDIA_TYPES
+                .into_iter()
+                .find(|(_, candidate)| *candidate == format)
+                .map(|(media_type, _)| media_type)
+                .expect("every other format has a media type"),
+        }
+    }
+
+    /// Whether the format is text, and so has a meaningful charset.
+    pub fn is_text(self) -> bool {
+        !matches!(self, Format::MessagePack | Format::Cbor)
+    }
+
+    /// Serializes `value`. Text formats are produced as UTF-8, and XML documents are wrapped in
+    /// a `root` element.
+    pub fn render(self, root: &str, value: &Value) -> Vec<u8> {
+        match self {
+            Format::Json => serde_json::to_vec(value).expect("JSON values always serialize"),
+            Format::PrettyJson => {
+                let mut json =
+                    serde_json::to_vec_pretty(value).expect("JSON values always serialize");
+                json.push(b'\n');
+                json
+            }
+            Format::Xml => to_xml(root, value).into_bytes(),
+            Format::Yaml => serde_yaml::to_string(value)
+                .expect("JSON values always serialize")
+                .into_bytes(),
+            Format::MessagePack => {
+                rmp_serde::to_vec_named(value).expect("JSON values always serialize")
+            }
+            Format::Cbor => {
+                let mut bytes = vec![];
+                ciborium::into_writer(value, &mut bytes).expect("JSON values always serialize");
+                bytes
+            }
+            Format::Text => {
+                let mut text = String::new();
+                write_text(&mut text, None, value);
+                text.into_bytes()
+            }
+        }
+    }
+
+    /// Parses a document in this format into its JSON equivalent, or describes why it is invalid.
+    ///
+    /// Byte strings, which MessagePack, CBOR and YAML can carry but JSON cannot, become base64
+    /// strings, and map keys that are not strings are converted to their JSON text. XML is mapped
+    /// as described on [`from_xml`].
+    pub fn parse(self, body: &[u8]) -> Result<Value, String> {
+        let parsed = match self {
+            Format::Json | Format::PrettyJson => {
+                serde_json::from_slice::<Structured>(body).map_err(|error| error.to_string())
+            }
+            Format::Xml => return from_xml(body),
+            Format::Yaml => {
+                serde_yaml::from_slice::<Structured>(body).map_err(|error| error.to_string())
+            }
+            Format::MessagePack => {
+                rmp_serde::from_slice::<Structured>(body).map_err(|error| error.to_string())
+            }
+            Format::Cbor => {
+                ciborium::from_reader::<Structured, _>(body).map_err(|error| error.to_string())
+            }
+            Format::Text => return Err("plain text has no structure to parse".to_owned()),
+        };
+        parsed.map(|Structured(value)| value)
+    }
+}
+
+/// Maps an XML document onto JSON: each element becomes an object holding its attributes as
+/// `@name` keys, its child elements by name (as arrays when repeated) and any text as `#text`.
+/// Elements with only text collapse to a string. The result is keyed by the root element's name.
+///
+/// Namespace prefixes are dropped, and documents with a DTD are rejected rather than expanded.
+fn from_xml(body: &[u8]) -> Result<Value, String> {
+    let text = std::str::from_utf8(body).map_err(|error| error.to_string())?;
+    let document = roxmltree::Document::parse(text).map_err(|error| error.to_string())?;
+    let root = document.root_element();
+    let mut json = Map::new();
+    json.insert(root.tag_name().name().to_owned(), element_to_json(root));
+    Ok(Value::Object(json))
+}
+
+fn element_to_json(element: roxmltree::Node) -> Value {
+    let mut json = Map::new();
+    for attribute in element.attributes() {
+        json.insert(
+            format!("@{}", attribute.name()),
+            Value::String(attribute.value().to_owned()),
+        );
+    }
+
+    let mut text = String::new();
+    for child in element.children() {
+        if child.is_element() {
+            let name = child.tag_name().name().to_owned();
+            let value = element_to_json(child);
+            match json.get_mut(&name) {
+                Some(Value::Array(values)) => values.push(value),
+                Some(existing) => *existing = Value::Array(vec![existing.take(), value]),
+                None => {
+                    json.insert(name, value);
+                }
+            }
+        } else if let Some(child_text) = child.text() {
+            text.push_str(child_text);
+        }
+    }
+
+    let text = text.trim();
+    if json.is_empty() {
+        return Value::String(text.to_owned());
+    }
+    if !text.is_empty() {
+        json.insert("#text".to_owned(), Value::String(text.to_owned()));
+    }
+    Value::Object(json)
+}
+
+/// A JSON value deserialized from a self-describing format that can express more than JSON.
+struct Structured(Value);
+
+impl<'de> Deserialize<'de> for Structured {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer
+            .deserialize_any(StructuredVisitor)
+            .map(Structured)
+    }
+}
+
+struct StructuredVisitor;
+
+impl<'de> Visitor<'de> for StructuredVisitor {
+    type Value = Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("any self-describing value")
+    }
+
+    fn visit_bool<E>(self, value: bool) -> Result<Value, E> {
+        Ok(Value::Bool(value))
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<Value, E> {
+        Ok(Value::Number(value.into()))
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<Value, E> {
+        Ok(Value::Number(value.into()))
+    }
+
+    fn visit_i128<E>(self, value: i128) -> Result<Value, E> {
+        Ok(i64::try_from(value)
+            .map(Value::from)
+            .unwrap_or_else(|_| Value::String(value.to_string())))
+    }
+
+    fn visit_u128<E>(self, value: u128) -> Result<Value, E> {
+        Ok(u64::try_from(value)
+            .map(Value::from)
+            .unwrap_or_else(|_| Value::String(value.to_string())))
+    }
+
+    fn visit_f64<E>(self, value: f64) -> Result<Value, E> {
+        // JSON has no NaN or infinities, so those are kept as their names.
+        Ok(Number::from_f64(value)
+            .map(Value::Number)
+            .unwrap_or_else(|| Value::String(value.to_string())))
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Value, E> {
+        Ok(Value::String(value.to_owned()))
+    }
+
+    fn visit_bytes<E>(self, value: &[u8]) -> Result<Value, E> {
+        Ok(Value::String(STANDARD.encode(value)))
+    }
+
+    fn visit_none<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_unit<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut values = vec![];
+        while let Some(Structured(value)) = seq.next_element()? {
+            values.push(value);
+        }
+        Ok(Value::Array(values))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let mut json = Map::new();
+        while let Some((Structured(key), Structured(value))) = map.next_entry()? {
+            let key = match key {
+                Value::String(key) => key,
+                key => key.to_string(),
+            };
+            json.insert(key, value);
+        }
+        Ok(Value::Object(json))
+    }
+
+    fn visit_enum<A: de::EnumAccess<'de>>(self, _data: A) -> Result<Value, A::Error> {
+        Err(de::Error::custom("tagged values are not supported"))
+    }
+}
+
+/// The format a response was asked for in, taken from the `format` query parameter or from
+/// `Accept`.
+///
+/// Responses are JSON unless the media types the client lists with the highest weight include
+/// one of the other formats. Wildcards do not count, so browsers and clients that accept
+/// anything keep getting JSON, and types no format is served as are never refused. Requests
+/// for an unknown format name are rejected with `400 Bad Request`.
+#[derive(Clone, Copy, Debug)]
+pub struct ResponseFormat {
+    pub format: Format,
+    media_type: &'static str,
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ResponseFormat
+where
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let query = parts.uri.query().unwrap_or_default();
+        let requested = serde_urlencoded::from_str::<Vec<(String, String)>>(query)
+            .unwrap_or_default()
+            .into_iter()
+            .find(|(key, _)| key == "format")
+            .map(|(_, name)| name);
+        if let Some(name) = requested {
+            return match Format::from_name(&name) {
+                Some(format) => Ok(Self {
+                    format,
+                    media_type: format.media_type(),
+                }),
+                None => {
+                    let names: Vec<_> = Format::NAMES.iter().map(|(name, _)| *name).collect();
+                    Err((
+                        StatusCode::BAD_REQUEST,
+                        [(header::CONTENT_TYPE, mime::TEXT_PLAIN.as_ref())],
+                        format!(
+                            "Unknown format \"{}\", expected one of: {}",
+                            name,
+                            names.join(", ")
+                        ),
+                    )
+                        .into_response())
+                }
+            };
+        }
+
+        let ranges = media_ranges(&parts.headers);
+        let listed = || {
+            ranges
+                .iter()
+                .filter(|range| range.type_ != "*" && range.subtype != "*" && range.quality > 0)
+        };
+        let highest = listed().map(|range| range.quality).max();
+        let (media_type, format) = listed()
+            .filter(|range| Some(range.quality) == highest)
+            .find_map(|range| {
+                Format::MEDIA_TYPES
+                    .into_iter()
+                    .chain([("text/plain", Format::Text)])
+                    .find(|(media_type, _)| {
+                        media_type.split_once('/').is_some_and(|(type_, subtype)| {
+                            type_.eq_ignore_ascii_case(range.type_)
+                                && subtype.eq_ignore_ascii_case(range.subtype)
+                        })
+                    })
+            })
+            .unwrap_or(("application/json", Format::Json));
+        Ok(Self { format, media_type })
+    }
+}
+
+impl ResponseFormat {
+    /// Renders `value` in the requested format. `root` names the document element of XML.
+    pub fn respond(self, root: &str, value: Value) -> Response {
+        let content_type = if self.format == Format::Text {
+            mime::TEXT_PLAIN_UTF_8.as_ref()
+        } else {
+            self.media_type
+        };
+        (
+            [
+                (header::CONTENT_TYPE, content_type),
+                (header::VARY, "accept"),
+            ],
+            self.format.render(root, &value),
+        )
+            .into_response()
+    }
+}
+
+fn write_text(text: &mut String, key: Option<&str>, value: &Value) {
+    match value {
+        Value::Object(fields) => write_fields(text, key, fields),
+        Value::Array(items) => {
+            for item in items {
+                write_text(text, key, item);
+            }
+        }
+        Value::String(value) => write_line(text, key, value),
+        Value::Null => write_line(text, key, ""),
+        value => write_line(text, key, &value.to_string()),
+    }
+}
+
+fn write_fields(text: &mut String, prefix: Option<&str>, fields: &Map<String, Value>) {
+    for (key, value) in fields {
+        match prefix {
+            Some(prefix) => write_text(text, Some(&format!("{}.{}", prefix, key)), value),
+            None => write_text(text, Some(key), value),
+        }
+    }
+}
+
+fn write_line(text: &mut String, key: Option<&str>, value: &str) {
+    if let Some(key) = key {
+        text.push_str(key);
+        text.push_str(": ");
+    }
+    // Keep one entry per line even when values contain line breaks.
+    text.push_str(&value.replace('\r', "\\r").replace('\n', "\\n"));
+    text.push('\n');
+}
+
+/// Renders a JSON value as XML: objects become child elements named after their keys, array
+/// items repeat the enclosing element, and `null` becomes an empty element.
+///
+/// Keys that are not valid XML names are written as `<item key="...">` instead.
+fn to_xml(root: &str, value: &Value) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\"?>\n");
+    write_element(&mut xml, root, value);
+    xml.push('\n');
+    xml
+}
+
+fn write_element(xml: &mut String, name: &str, value: &Value) {
+    if let Value::Array(items) = value {
+        for item in items {
+            write_element(xml, name, item);
+        }
+        return;
+    }
+
+    if is_xml_name(name) {
+        xml.push_str(&format!("<{}>", name));
+    } else {
+        xml.push_str(&format!("<item key=\"{}\">", escape_xml(name)));
+    }
+    match value {
+        Value::Null => {}
+        Value::Bool(value) => xml.push_str(&value.to_string()),
+        Value::Number(value) => xml.push_str(&value.to_string()),
+        Value::String(value) => xml.push_str(&escape_xml(value)),
+        Value::Object(fields) => {
+            for (key, value) in fields {
+                write_element(xml, key, value);
+            }
+        }
+        Value::Array(_) => unreachable!("arrays are flattened above"),
+    }
+    if is_xml_name(name) {
+        xml.push_str(&format!("</{}>", name));
+    } else {
+        xml.push_str("</item>");
+    }
+}
+
+// A conservative subset of the XML `Name` production, which is all header names and the
+// like need.
+fn is_xml_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|char| char.is_ascii_alphanumeric() || "_-.".contains(char))
+        && !name.to_ascii_lowercase().starts_with("xml")
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for char in text.chars() {
+        match char {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            // Control characters other than whitespace cannot appear in XML 1.0 at all.
+            char if char.is_control() && !matches!(char, '\t' | '\n' | '\r') => {
+                escaped.push('\u{fffd}')
+            }
+            char => escaped.push(char),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn renders_xml() {
+        let value = json!({
+            "name": "a < b & c",
+            "tags": ["x", "y"],
+            "nested": {"empty": null, "count": 2},
+            "not a name": true,
+        });
+
+        assert_eq!(
+            to_xml("root", &value),
+            "<?xml version=\"1.0\"?>\n<root>\
+            <name>a &lt; b &amp; c</name>\
+            <nested><count>2</count><empty></empty></nested>\
+            <item key=\"not a name\">true</item>\
+            <tags>x</tags><tags>y</tags>\
+            </root>\n"
+        );
+    }
+
+    #[test]
+    fn renders_text() {
+        let value = json!({
+            "headers": {"accept": "*/*", "x-multi": "a\nb"},
+            "origin": ["10.0.0.1", "10.0.0.2"],
+            "ok": true,
+        });
+
+        assert_eq!(
+            String::from_utf8(Format::Text.render("root", &value)).unwrap(),
+            "headers.accept: */*\n\
+            headers.x-multi: a\\nb\n\
+            ok: true\n\
+            origin: 10.0.0.1\n\
+            origin: 10.0.0.2\n"
+        );
+    }
+
+    #[test]
+    fn looks_up_format_names() {
+        assert_eq!(Format::from_name("MsgPack"), Some(Format::MessagePack));
+        assert_eq!(Format::from_name("html"), None);
+        assert_eq!(Format::PrettyJson.media_type(), "application/json");
+        assert_eq!(Format::Yaml.media_type(), "application/yaml");
+    }
+
+    #[test]
+    fn parses_xml() {
+        let xml = br#"<?xml version="1.0"?>
+            <slideshow title="Sample" xmlns:x="urn:x">
+              <slide><title>One</title></slide>
+              <slide type="all"><title>Two</title><x:item>a &amp; b</x:item></slide>
+              <empty/>
+            </slideshow>"#;
+
+        assert_eq!(
+            Format::Xml.parse(xml).unwrap(),
+            json!({
+                "slideshow": {
+                    "@title": "Sample",
+                    "slide": [
+                        {"title": "One"},
+                        {"@type": "all", "title": "Two", "item": "a & b"},
+                    ],
+                    "empty": "",
+                }
+            })
+        );
+        assert!(Format::Xml.parse(b"<open>").is_err());
+        assert!(Format::Xml
+            .parse(b"<!DOCTYPE x [<!ENTITY a \"b\">]><x>&a;</x>")
+            .is_err());
+    }
+
+    #[test]
+    fn parses_values_json_cannot_express() {
+        let mut cbor = vec![];
+        ciborium::into_writer(
+            &ciborium::Value::Map(vec![
+                (
+                    ciborium::Value::Integer(1.into()),
+                    ciborium::Value::Bytes(vec![0xff, 0x00]),
+                ),
+                (
+                    ciborium::Value::Text("nan".to_owned()),
+                    ciborium::Value::Float(f64::NAN),
+                ),
+            ]),
+            &mut cbor,
+        )
+        .unwrap();
+
+        assert_eq!(
+            Format::Cbor.parse(&cbor).unwrap(),
+            json!({"1": "/wA=", "nan": "NaN"})
+        );
+        assert!(Format::MessagePack.parse(&[0xc1]).is_err());
+    }
+
+    #[test]
+    fn binary_formats_round_trip() {
+        let value = json!({"origin": "10.0.0.1", "ports": [80, 443]});
+
+        let msgpack = Format::MessagePack.render("root", &value);
+        assert_eq!(rmp_serde::from_slice::<Value>(&msgpack).unwrap(), value);
+        let cbor = Format::Cbor.render("root", &value);
+        assert_eq!(ciborium::from_reader::<Value, _>(&cbor[..]).unwrap(), value);
+        let yaml = Format::Yaml.render("root", &value);
+        assert_eq!(serde_yaml::from_slice::<Value>(&yaml).unwrap(), value);
+    }
+}
@@ -1,3 +1,12 @@
+mod charset;
+mod compression;
+mod conditional;
+mod config;
+mod content_coding;
+mod echo;
+mod format;
+mod negotiation;
+mod range;
 mod routes;
 mod server;
 
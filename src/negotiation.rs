@@ -0,0 +1,349 @@
+use axum::{
+    http::{header, HeaderMap, HeaderName, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use mime::Mime;
+use serde_json::json;
+
+/// One element of a comma-separated header with an optional weight, such as `gzip;q=0.8`.
+pub struct Weighted<'a> {
+    pub value: &'a str,
+    /// The `q` weight scaled to an integer between 0 and 1000.
+    pub quality: u16,
+}
+
+/// Splits every instance of the `name` header into weighted elements. Elements with an invalid
+/// `q` parameter are dropped, as they cannot be ranked.
+pub fn weighted_list<'a>(headers: &'a HeaderMap, name: &HeaderName) -> Vec<Weighted<'a>> {
+    headers
+        .get_all(name)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|element| {
+            let mut parts = element.split(';').map(str::trim);
+            let value = parts.next().filter(|value| !value.is_empty())?;
+            let quality = match parts.find_map(|param| {
+                param
+                    .strip_prefix("q=")
+                    .or_else(|| param.strip_prefix("Q="))
+            }) {
+                Some(quality) => parse_quality(quality)?,
+                None => 1000,
+            };
+            Some(Weighted { value, quality })
+        })
+        .collect()
+}
+
+/// A media range from an `Accept` header, such as `text/*` or `text/html;level=1`.
+pub struct MediaRange<'a> {
+    pub type_: &'a str,
+    pub subtype: &'a str,
+    /// Parameters before the weight, with surrounding quotes removed from their values.
+    pub params: Vec<(&'a str, &'a str)>,
+    /// The `q` weight scaled to an integer between 0 and 1000.
+    pub quality: u16,
+}
+
+impl MediaRange<'_> {
+    /// How precisely the range matches `mime`, or `None` when it does not match at all. Full
+    /// types outrank `type/*`, which outranks `*/*`, and each matching parameter adds one.
+    fn specificity(&self, mime: &Mime) -> Option<usize> {
+        if self.type_ == "*" {
+            return (self.subtype == "*").then_some(0);
+        }
+        if !self.type_.eq_ignore_ascii_case(mime.type_().as_str()) {
+            return None;
+        }
+        if self.subtype == "*" {
+            return Some(1);
+        }
+        let (_, subtype) = mime.essence_str().split_once('/')?;
+        let params_match = self.params.iter().all(|(name, value)| {
+            mime.get_param(*name)
+                .is_some_and(|param| param.as_str().eq_ignore_ascii_case(value))
+        });
+        (self.subtype.eq_ignore_ascii_case(subtype) && params_match)
+            .then_some(2 + self.params.len())
+    }
+}
+
+/// Parses every `Accept` header into media ranges. Malformed ranges and ranges with an invalid
+/// weight are dropped, and extension parameters after the weight are ignored.
+pub fn media_ranges(headers: &HeaderMap) -> Vec<MediaRange<'_>> {
+    headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| split_unquoted(value, ','))
+        .filter_map(|element| {
+            let mut parts = split_unquoted(element, ';').map(str::trim);
+            let (type_, subtype) = parts.next()?.split_once('/')?;
+            let (type_, subtype) = (type_.trim(), subtype.trim());
+            if type_.is_empty() || subtype.is_empty() || (type_ == "*" && subtype != "*") {
+                return None;
+            }
+
+            let mut params = vec![];
+            let mut quality = 1000;
+            for param in parts {
+                let (name, value) = param.split_once('=')?;
+                let (name, value) = (name.trim(), value.trim());
+                if name.eq_ignore_ascii_case("q") {
+                    quality = parse_quality(value)?;
+                    break;
+                }
+                let value = value
+                    .strip_prefix('"')
+                    .and_then(|value| value.strip_suffix('"'))
+                    .unwrap_or(value);
+                params.push((name, value));
+            }
+
+            Some(MediaRange {
+                type_,
+                subtype,
+                params,
+                quality,
+            })
+        })
+        .collect()
+}
+
+/// Picks the media type the client prefers from `available`, which is in server preference
+/// order. Returns `None` when the client accepts none of them.
+pub fn preferred_media_type<'m>(headers: &HeaderMap, available: &'m [Mime]) -> Option<&'m Mime> {
+    let ranges = media_ranges(headers);
+    select(&ranges, available, |range, mime| {
+        range
+            .specificity(mime)
+            .map(|specificity| (specificity, range.quality))
+    })
+}
+
+/// Picks the language the client prefers from `available` language tags, matching ranges such
+/// as `en` against `en-GB` by prefix (RFC 4647 3.3.1).
+pub fn preferred_language<'a>(headers: &HeaderMap, available: &'a [&'a str]) -> Option<&'a str> {
+    let ranges = weighted_list(headers, &header::ACCEPT_LANGUAGE);
+    select(&ranges, available, |range, tag| {
+        let matches = range.value == "*"
+            || range.value.eq_ignore_ascii_case(tag)
+            || tag
+                .get(..range.value.len() + 1)
+                .is_some_and(|prefix| prefix.eq_ignore_ascii_case(&format!("{}-", range.value)));
+        matches.then_some((range.value.len(), range.quality))
+    })
+    .copied()
+}
+
+/// Picks the charset the client prefers from `available`.
+pub fn preferred_charset<'a>(headers: &HeaderMap, available: &'a [&'a str]) -> Option<&'a str> {
+    let ranges = weighted_list(headers, &header::ACCEPT_CHARSET);
+    select(&ranges, available, |range, charset| {
+        if range.value == "*" {
+            Some((0, range.quality))
+        } else {
+            range
+                .value
+                .eq_ignore_ascii_case(charset)
+                .then_some((1, range.quality))
+        }
+    })
+    .copied()
+}
+
+/// A `406 Not Acceptable` response listing the supported values under `field`, in the same
+/// shape as `/status/406`.
+pub fn not_acceptable(message: &str, field: &str, supported: &[&str]) -> Response {
+    (
+        StatusCode::NOT_ACCEPTABLE,
+        Json(json!({ "message": message, field: supported })),
+    )
+        .into_response()
+}
+
+/// Weighs each candidate by the most specific range that matches it (RFC 9110 12.5.1) and
+/// returns the first with the highest non-zero weight. Without any ranges, everything is
+/// acceptable and the first candidate wins.
+fn select<'a, R, T>(
+    ranges: &[R],
+    available: &'a [T],
+    weigh: impl Fn(&R, &T) -> Option<(usize, u16)>,
+) -> Option<&'a T> {
+    if ranges.is_empty() {
+        return available.first();
+    }
+
+    let mut best: Option<(&T, u16)> = None;
+    for candidate in available {
+        let quality = ranges
+            .iter()
+            .filter_map(|range| weigh(range, candidate))
+            .max_by_key(|(specificity, _)| *specificity)
+            .map(|(_, quality)| quality);
+        match quality {
+            Some(quality) if quality > best.map_or(0, |(_, best)| best) => {
+                best = Some((candidate, quality))
+            }
+            _ => {}
+        }
+    }
+    best.map(|(candidate, _)| candidate)
+}
+
+/// Splits `value` on `separator`, except inside quoted strings.
+fn split_unquoted(value: &str, separator: char) -> impl Iterator<Item = &str> {
+    let mut quoted = false;
+    let mut escaped = false;
+    value
+        .split(move |char| {
+            if escaped {
+                escaped = false;
+            } else if quoted && char == '\\' {
+                escaped = true;
+            } else if char == '"' {
+                quoted = !quoted;
+            }
+            char == separator && !quoted
+        })
+        .filter(|part| !part.trim().is_empty())
+}
+
+/// Parses a `qvalue` (RFC 9110 12.4.2): `0` to `1` with at most three decimal places.
+fn parse_quality(value: &str) -> Option<u16> {
+    let (integer, fraction) = value.split_once('.').unwrap_or((value, ""));
+    if fraction.len() > 3 || !fraction.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    let fraction = format!("{:0<3}", fraction).parse::<u16>().ok()?;
+    match integer {
+        "0" => Some(fraction),
+        "1" if fraction == 0 => Some(1000),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::{header, HeaderValue};
+
+    #[test]
+    fn parses_weights() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::ACCEPT_ENCODING,
+            HeaderValue::from_static("gzip;q=0.5, br, zstd ; Q=0, deflate;q=1.0, ,*;q=0.001"),
+        );
+        headers.append(
+            header::ACCEPT_ENCODING,
+            HeaderValue::from_static("identity;q=.5"),
+        );
+
+        let weighted: Vec<_> = weighted_list(&headers, &header::ACCEPT_ENCODING)
+            .into_iter()
+            .map(|weighted| (weighted.value, weighted.quality))
+            .collect();
+
+        assert_eq!(
+            weighted,
+            [
+                ("gzip", 500),
+                ("br", 1000),
+                ("zstd", 0),
+                ("deflate", 1000),
+                ("*", 1)
+            ]
+        );
+    }
+
+    fn accept(name: HeaderName, value: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(name, HeaderValue::from_static(value));
+        headers
+    }
+
+    #[test]
+    fn parses_media_ranges() {
+        let headers = accept(
+            header::ACCEPT,
+            "text/html;level=1;q=0.5;ext=x, application/*, */*;q=0.1, text/plain;format=\"a,b\", */json, bad",
+        );
+
+        let ranges: Vec<_> = media_ranges(&headers)
+            .into_iter()
+            .map(|range| (range.type_, range.subtype, range.params, range.quality))
+            .collect();
+
+        assert_eq!(
+            ranges,
+            [
+                ("text", "html", vec![("level", "1")], 500),
+                ("application", "*", vec![], 1000),
+                ("*", "*", vec![], 100),
+                ("text", "plain", vec![("format", "a,b")], 1000),
+            ]
+        );
+    }
+
+    #[test]
+    fn prefers_most_specific_media_range() {
+        let available: Vec<Mime> = ["application/json", "application/xml", "text/plain"]
+            .iter()
+            .map(|mime| mime.parse().unwrap())
+            .collect();
+        let preferred = |value| {
+            preferred_media_type(&accept(header::ACCEPT, value), &available)
+                .map(|mime| mime.essence_str().to_owned())
+        };
+
+        assert_eq!(
+            preferred("application/*;q=0.5, application/json;q=0.2, text/*;q=0.4").as_deref(),
+            Some("application/xml")
+        );
+        assert_eq!(preferred("*/*").as_deref(), Some("application/json"));
+        assert_eq!(
+            preferred("text/plain;charset=utf-8, */*;q=0.1").as_deref(),
+            Some("application/json")
+        );
+        assert_eq!(preferred("image/*, application/*;q=0"), None);
+        assert_eq!(
+            preferred_media_type(&HeaderMap::new(), &available),
+            available.first()
+        );
+    }
+
+    #[test]
+    fn matches_language_prefixes() {
+        let available = ["en", "fr-CA", "fr"];
+        let preferred =
+            |value| preferred_language(&accept(header::ACCEPT_LANGUAGE, value), &available);
+
+        assert_eq!(preferred("fr"), Some("fr-CA"));
+        assert_eq!(preferred("fr-ca;q=0.5, fr"), Some("fr"));
+        assert_eq!(preferred("de, *;q=0.1"), Some("en"));
+        assert_eq!(preferred("f"), None);
+        assert_eq!(preferred("de"), None);
+    }
+
+    #[test]
+    fn negotiates_charsets() {
+        let available = ["utf-8", "utf-16"];
+        let preferred =
+            |value| preferred_charset(&accept(header::ACCEPT_CHARSET, value), &available);
+
+        assert_eq!(preferred("UTF-16, utf-8;q=0.5"), Some("utf-16"));
+        assert_eq!(preferred("iso-8859-1, *;q=0.1"), Some("utf-8"));
+        assert_eq!(preferred("utf-8;q=0, *"), Some("utf-16"));
+        assert_eq!(preferred("iso-8859-1"), None);
+    }
+
+    #[test]
+    fn rejects_invalid_weights() {
+        for quality in ["1.5", "2", "0.0001", "-0", "", "0.x"] {
+            assert_eq!(parse_quality(quality), None, "{}", quality);
+        }
+    }
+}
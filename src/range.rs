@@ -0,0 +1,361 @@
+use axum::{
+    body::Bytes,
+    headers::{ETag, HeaderMapExt, IfRange},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+};
+use std::time::SystemTime;
+
+/// An inclusive span of byte offsets within a representation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl ByteRange {
+    pub fn content_range(&self, complete_length: u64) -> String {
+        format!("bytes {}-{}/{}", self.start, self.end, complete_length)
+    }
+}
+
+/// The part of a representation to send in answer to a request's `Range` and `If-Range`.
+pub struct Selection {
+    /// `200 OK`, `206 Partial Content` or `416 Range Not Satisfiable`.
+    pub status: StatusCode,
+    pub content_range: Option<String>,
+    /// The media type of the body when it is `multipart/byteranges` rather than the
+    /// representation itself.
+    pub multipart_type: Option<String>,
+    pub body: Bytes,
+}
+
+/// How to combine several ranges into one `multipart/byteranges` body.
+pub struct Multipart<'a> {
+    /// Must not occur in the representation.
+    pub boundary: &'a str,
+    /// The representation's media type, which labels each part.
+    pub content_type: &'a str,
+}
+
+/// Selects the part of `representation` that the request headers ask for.
+///
+/// `Range` only applies when `If-Range` is absent or matches `etag`, or `last_modified` exactly
+/// when the representation has such a date (RFC 9110 section 13.1.5). A single range is sent as
+/// `206 Partial Content`, and a set of ranges that all miss the representation as `416`.
+/// Several ranges become one `multipart/byteranges` body if `multipart` says how and they add
+/// up to no more than the representation. Otherwise the whole representation is sent, which
+/// RFC 9110 section 14.2 allows, so that overlapping ranges cannot amplify a response.
+pub fn select(
+    request_headers: &HeaderMap,
+    etag: &ETag,
+    last_modified: Option<SystemTime>,
+    representation: &Bytes,
+    multipart: Option<Multipart>,
+) -> Selection {
+    let complete_length = representation.len() as u64;
+    let full = Selection {
+        status: StatusCode::OK,
+        content_range: None,
+        multipart_type: None,
+        body: representation.clone(),
+    };
+    let if_range_matches = request_headers
+        .typed_get::<IfRange>()
+        .is_none_or(|if_range| {
+            if_range == IfRange::etag(etag.clone())
+                || last_modified.is_some_and(|date| if_range == IfRange::date(date))
+        });
+    if !if_range_matches {
+        return full;
+    }
+
+    match resolve(request_headers.get(header::RANGE), complete_length) {
+        RangeRequest::Partial(ranges) if ranges.len() == 1 => Selection {
+            status: StatusCode::PARTIAL_CONTENT,
+            content_range: Some(ranges[0].content_range(complete_length)),
+            multipart_type: None,
+            body: representation.slice(ranges[0].start as usize..=ranges[0].end as usize),
+        },
+        RangeRequest::Partial(ranges) => match multipart {
+            Some(multipart)
+                if ranges
+                    .iter()
+                    .map(|range| range.end - range.start + 1)
+                    .sum::<u64>()
+                    <= complete_length =>
+            {
+                Selection {
+                    status: StatusCode::PARTIAL_CONTENT,
+                    content_range: None,
+                    multipart_type: Some(format!(
+                        "multipart/byteranges; boundary={}",
+                        multipart.boundary
+                    )),
+                    body: multipart_byteranges(
+                        multipart.boundary,
+                        multipart.content_type,
+                        &ranges,
+                        representation,
+                    )
+                    .into(),
+                }
+            }
+            _ => full,
+        },
+        RangeRequest::Unsatisfiable => Selection {
+            status: StatusCode::RANGE_NOT_SATISFIABLE,
+            content_range: Some(format!("bytes */{}", complete_length)),
+            multipart_type: None,
+            body: Bytes::new(),
+        },
+        RangeRequest::Full => full,
+    }
+}
+
+/// Builds a `multipart/byteranges` body holding each of `ranges` of `representation`, with one
+/// part per range in the order given (RFC 9110 section 14.6).
+///
+/// `boundary` must not occur in the representation.
+fn multipart_byteranges(
+    boundary: &str,
+    content_type: &str,
+    ranges: &[ByteRange],
+    representation: &[u8],
+) -> Vec<u8> {
+    let complete_length = representation.len() as u64;
+    let mut body = vec![];
+    for range in ranges {
+        body.extend_from_slice(
+            format!(
+                "--{}\r\nContent-Type: {}\r\nContent-Range: {}\r\n\r\n",
+                boundary,
+                content_type,
+                range.content_range(complete_length)
+            )
+            .as_bytes(),
+        );
+        body.extend_from_slice(&representation[range.start as usize..=range.end as usize]);
+        body.extend_from_slice(b"\r\n");
+    }
+    body.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+    body
+}
+
+/// What a `Range` header asks for once applied to a representation of known length.
+#[derive(Debug, PartialEq, Eq)]
+pub enum RangeRequest {
+    /// No usable `Range` header; the full representation should be sent.
+    Full,
+    /// One or more satisfiable ranges, in the order they were requested.
+    Partial(Vec<ByteRange>),
+    /// The header was valid but none of its ranges overlap the representation.
+    Unsatisfiable,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum RangeSpec {
+    Bounded { first: u64, last: Option<u64> },
+    Suffix(u64),
+}
+
+/// Resolves a `Range` header against a representation of `complete_length` bytes.
+///
+/// Headers that are malformed or use a unit other than `bytes` are ignored, as permitted by
+/// RFC 9110 section 14.2.
+pub fn resolve(header: Option<&HeaderValue>, complete_length: u64) -> RangeRequest {
+    let specs = match header.and_then(|value| value.to_str().ok()).and_then(parse) {
+        Some(specs) => specs,
+        None => return RangeRequest::Full,
+    };
+
+    let ranges: Vec<ByteRange> = specs
+        .into_iter()
+        .filter_map(|spec| match spec {
+            RangeSpec::Bounded { first, last } if first < complete_length => Some(ByteRange {
+                start: first,
+                end: last.map_or(complete_length - 1, |last| last.min(complete_length - 1)),
+            }),
+            RangeSpec::Suffix(length) if length > 0 && complete_length > 0 => Some(ByteRange {
+                start: complete_length - length.min(complete_length),
+                end: complete_length - 1,
+            }),
+            _ => None,
+        })
+        .collect();
+
+    if ranges.is_empty() {
+        RangeRequest::Unsatisfiable
+    } else {
+        RangeRequest::Partial(ranges)
+    }
+}
+
+fn parse(value: &str) -> Option<Vec<RangeSpec>> {
+    let (unit, set) = value.split_once('=')?;
+    if !unit.trim().eq_ignore_ascii_case("bytes") {
+        return None;
+    }
+
+    let mut specs = vec![];
+    for spec in set
+        .split(',')
+        .map(str::trim)
+        .filter(|spec| !spec.is_empty())
+    {
+        let (first, last) = spec.split_once('-')?;
+        let spec = if first.is_empty() {
+            RangeSpec::Suffix(parse_digits(last)?)
+        } else {
+            let first = parse_digits(first)?;
+            let last = if last.is_empty() {
+                None
+            } else {
+                Some(parse_digits(last)?)
+            };
+            if last.is_some_and(|last| last < first) {
+                return None;
+            }
+            RangeSpec::Bounded { first, last }
+        };
+        specs.push(spec);
+    }
+
+    if specs.is_empty() {
+        None
+    } else {
+        Some(specs)
+    }
+}
+
+fn parse_digits(value: &str) -> Option<u64> {
+    if value.is_empty() || !value.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    value.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve_str(value: &str, complete_length: u64) -> RangeRequest {
+        resolve(
+            Some(&HeaderValue::from_str(value).unwrap()),
+            complete_length,
+        )
+    }
+
+    #[test]
+    fn resolves_bounded_open_and_suffix_ranges() {
+        assert_eq!(
+            resolve_str("bytes=0-9, 90-, -5", 100),
+            RangeRequest::Partial(vec![
+                ByteRange { start: 0, end: 9 },
+                ByteRange { start: 90, end: 99 },
+                ByteRange { start: 95, end: 99 },
+            ])
+        );
+    }
+
+    #[test]
+    fn clamps_ranges_to_the_representation() {
+        assert_eq!(
+            resolve_str("bytes=50-500,-1000", 100),
+            RangeRequest::Partial(vec![
+                ByteRange { start: 50, end: 99 },
+                ByteRange { start: 0, end: 99 },
+            ])
+        );
+    }
+
+    #[test]
+    fn reports_unsatisfiable_ranges() {
+        assert_eq!(resolve_str("bytes=100-", 100), RangeRequest::Unsatisfiable);
+        assert_eq!(resolve_str("bytes=-0", 100), RangeRequest::Unsatisfiable);
+        assert_eq!(resolve_str("bytes=0-", 0), RangeRequest::Unsatisfiable);
+    }
+
+    #[test]
+    fn builds_multipart_byteranges() {
+        let body = multipart_byteranges(
+            "sep",
+            "text/plain",
+            &[
+                ByteRange { start: 0, end: 1 },
+                ByteRange { start: 8, end: 9 },
+            ],
+            b"0123456789",
+        );
+        assert_eq!(
+            String::from_utf8(body).unwrap(),
+            "--sep\r\nContent-Type: text/plain\r\nContent-Range: bytes 0-1/10\r\n\r\n01\r\n\
+             --sep\r\nContent-Type: text/plain\r\nContent-Range: bytes 8-9/10\r\n\r\n89\r\n\
+             --sep--\r\n"
+        );
+    }
+
+    #[test]
+    fn ignores_invalid_headers() {
+        assert_eq!(resolve(None, 100), RangeRequest::Full);
+        assert_eq!(resolve_str("items=0-1", 100), RangeRequest::Full);
+        assert_eq!(resolve_str("bytes=5-1", 100), RangeRequest::Full);
+        assert_eq!(resolve_str("bytes=+1-2", 100), RangeRequest::Full);
+        assert_eq!(resolve_str("bytes=", 100), RangeRequest::Full);
+    }
+
+    #[test]
+    fn applies_ranges_only_when_if_range_matches() {
+        let etag: ETag = "\"v1\"".parse().unwrap();
+        let modified = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000_000);
+        let representation = Bytes::from_static(b"0123456789");
+        let select_with = |if_range: Option<&'static str>| {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::RANGE, HeaderValue::from_static("bytes=2-4"));
+            if let Some(if_range) = if_range {
+                headers.insert(header::IF_RANGE, HeaderValue::from_static(if_range));
+            }
+            select(&headers, &etag, Some(modified), &representation, None)
+        };
+
+        for if_range in [None, Some("\"v1\""), Some("Sun, 09 Sep 2001 01:46:40 GMT")] {
+            let selection = select_with(if_range);
+            assert_eq!(
+                selection.status,
+                StatusCode::PARTIAL_CONTENT,
+                "{:?}",
+                if_range
+            );
+            assert_eq!(selection.content_range.as_deref(), Some("bytes 2-4/10"));
+            assert_eq!(selection.body, "234");
+        }
+        for if_range in [Some("\"v2\""), Some("Sun, 09 Sep 2001 01:46:41 GMT")] {
+            let selection = select_with(if_range);
+            assert_eq!(selection.status, StatusCode::OK, "{:?}", if_range);
+            assert_eq!(selection.content_range, None);
+            assert_eq!(selection.body, representation);
+        }
+    }
+
+    #[test]
+    fn answers_several_ranges_in_full_unless_multipart() {
+        let etag: ETag = "\"v1\"".parse().unwrap();
+        let representation = Bytes::from_static(b"0123456789");
+        let mut headers = HeaderMap::new();
+        headers.insert(header::RANGE, HeaderValue::from_static("bytes=0-1,8-9"));
+
+        let full = select(&headers, &etag, None, &representation, None);
+        assert_eq!(full.status, StatusCode::OK);
+        assert_eq!(full.body, representation);
+
+        let multipart = Multipart {
+            boundary: "sep",
+            content_type: "text/plain",
+        };
+        let parts = select(&headers, &etag, None, &representation, Some(multipart));
+        assert_eq!(parts.status, StatusCode::PARTIAL_CONTENT);
+        assert_eq!(
+            parts.multipart_type.as_deref(),
+            Some("multipart/byteranges; boundary=sep")
+        );
+    }
+}
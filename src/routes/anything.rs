@@ -0,0 +1,254 @@
+use crate::echo::{BodyEcho, Echo, RequestEcho};
+use axum::{routing::any, Json, Router};
+
+pub fn routes() -> Router {
+    Router::new()
+        .route("/anything", any(anything))
+        .route("/anything/*anything", any(anything))
+}
+
+async fn anything(request: RequestEcho, body: BodyEcho) -> Json<Echo> {
+    Json(Echo { request, body })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content_coding::ContentCoding;
+    use axum::{
+        body::Body,
+        extract::connect_info::MockConnectInfo,
+        http::{header, HeaderValue, Method, Request, StatusCode},
+    };
+    use serde_json::{json, Value};
+    use std::net::SocketAddr;
+    use tower::ServiceExt;
+
+    fn app() -> Router {
+        routes().layer(MockConnectInfo(SocketAddr::from(([10, 10, 32, 1], 59351))))
+    }
+
+    async fn json_body(response: axum::response::Response) -> Value {
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn echoes_request() {
+        let response = app()
+            .oneshot(
+                Request::builder()
+                    .method(Method::PUT)
+                    .uri("/anything/foo?a=1&b=2&a=3")
+                    .header(header::HOST, "httpbin.test")
+                    .header(header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                    .body(Body::from(r#"{"hello": "world"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE),
+            Some(&HeaderValue::from_static(mime::APPLICATION_JSON.as_ref()))
+        );
+
+        let echo = json_body(response).await;
+        assert_eq!(echo["method"], "PUT");
+        assert_eq!(echo["url"], "http://httpbin.test/anything/foo?a=1&b=2&a=3");
+        assert_eq!(echo["args"]["a"], serde_json::json!(["1", "3"]));
+        assert_eq!(echo["args"]["b"], "2");
+        assert_eq!(echo["data"], r#"{"hello": "world"}"#);
+        assert_eq!(echo["json"]["hello"], "world");
+        assert_eq!(echo["origin"], "10.10.32.1");
+        assert!(echo.get("content_encoding").is_none());
+    }
+
+    #[tokio::test]
+    async fn echoes_forms() {
+        let response = app()
+            .oneshot(
+                Request::builder()
+                    .method(Method::POST)
+                    .uri("/anything")
+                    .header(header::CONTENT_TYPE, "multipart/form-data; boundary=X")
+                    .body(Body::from(
+                        "--X\r\nContent-Disposition: form-data; name=\"field\"\r\n\r\nvalue\r\n\
+                         --X\r\nContent-Disposition: form-data; name=\"upload\"; filename=\"a.txt\"\r\n\
+                         Content-Type: text/plain\r\n\r\ncontents\r\n--X--\r\n",
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let echo = json_body(response).await;
+        assert_eq!(echo["form"]["field"], "value");
+        assert_eq!(echo["files"]["upload"], "contents");
+        assert_eq!(echo["data"], "");
+    }
+
+    async fn post(content_type: &str, body: impl Into<Body>) -> Value {
+        let response = app()
+            .oneshot(
+                Request::builder()
+                    .method(Method::POST)
+                    .uri("/anything")
+                    .header(header::CONTENT_TYPE, content_type)
+                    .body(body.into())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        json_body(response).await
+    }
+
+    #[tokio::test]
+    async fn parses_structured_bodies() {
+        let expected = json!({"name": "httpbin", "tags": ["a", "b"]});
+        let mut cbor = vec![];
+        ciborium::into_writer(&expected, &mut cbor).unwrap();
+
+        for (content_type, body) in [
+            (
+                "application/yaml",
+                b"name: httpbin\ntags: [a, b]\n".to_vec(),
+            ),
+            (
+                "application/msgpack",
+                rmp_serde::to_vec_named(&expected).unwrap(),
+            ),
+            ("application/cbor", cbor),
+        ] {
+            let echo = post(content_type, body).await;
+            assert_eq!(echo["parsed"], expected, "{}", content_type);
+            assert!(echo.get("parse_error").is_none(), "{}", content_type);
+        }
+
+        let echo = post(
+            "application/atom+xml",
+            "<feed><title>httpbin</title></feed>",
+        )
+        .await;
+        assert_eq!(echo["parsed"], json!({"feed": {"title": "httpbin"}}));
+        assert_eq!(echo["data"], "<feed><title>httpbin</title></feed>");
+
+        let echo = post("application/x-ndjson", "{\"a\":1}\n\n[2]\n").await;
+        assert_eq!(echo["parsed"], json!([{"a": 1}, [2]]));
+        assert_eq!(echo["json"], Value::Null);
+    }
+
+    #[tokio::test]
+    async fn reports_parse_errors_per_format() {
+        for (content_type, body, format) in [
+            ("text/xml", &b"<a><b></a>"[..], "xml"),
+            ("application/yaml", b"key: [unclosed", "yaml"),
+            ("application/msgpack", b"\xc1", "msgpack"),
+            ("application/cbor", b"\xff", "cbor"),
+            ("application/x-ndjson", b"{}\n{oops}\n", "ndjson"),
+        ] {
+            let echo = post(content_type, body).await;
+            assert_eq!(echo["parsed"], Value::Null, "{}", content_type);
+            assert_eq!(echo["parse_error"]["format"], format);
+            assert!(echo["parse_error"]["message"].is_string());
+        }
+
+        let echo = post("application/x-ndjson", "{}\n{oops}\n").await;
+        assert!(echo["parse_error"]["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("line 2:"));
+
+        let echo = post("text/plain", "not structured").await;
+        assert!(echo.get("parsed").is_none());
+    }
+
+    #[tokio::test]
+    async fn decodes_compressed_bodies() {
+        let form = b"name=httpbin&language=rust";
+        let gzipped = ContentCoding::Gzip.encode(form).await;
+        let encoded = ContentCoding::Brotli.encode(&gzipped).await;
+
+        let response = app()
+            .oneshot(
+                Request::builder()
+                    .method(Method::POST)
+                    .uri("/anything")
+                    .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+                    .header(header::CONTENT_ENCODING, "gzip, br")
+                    .body(Body::from(encoded.clone()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let echo = json_body(response).await;
+        assert_eq!(echo["form"]["name"], "httpbin");
+        assert_eq!(echo["form"]["language"], "rust");
+        assert_eq!(
+            echo["content_encoding"]["codings"],
+            serde_json::json!(["gzip", "br"])
+        );
+        assert_eq!(echo["content_encoding"]["encoded_size"], encoded.len());
+        assert_eq!(echo["content_encoding"]["decoded_size"], form.len());
+    }
+
+    #[tokio::test]
+    async fn rejects_unsupported_encodings() {
+        let response = app()
+            .oneshot(
+                Request::builder()
+                    .method(Method::POST)
+                    .uri("/anything")
+                    .header(header::CONTENT_ENCODING, "compress")
+                    .body(Body::from("data"))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert!(response.headers().get(header::ACCEPT_ENCODING).is_some());
+    }
+
+    #[tokio::test]
+    async fn rejects_corrupt_bodies() {
+        let response = app()
+            .oneshot(
+                Request::builder()
+                    .method(Method::POST)
+                    .uri("/anything")
+                    .header(header::CONTENT_ENCODING, "zstd")
+                    .body(Body::from("not zstd"))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn rejects_bodies_that_decompress_beyond_the_limit() {
+        let limit = crate::config::get().max_decompressed_size as usize;
+        let encoded = ContentCoding::Gzip.encode(&vec![0; limit + 1]).await;
+
+        let response = app()
+            .oneshot(
+                Request::builder()
+                    .method(Method::POST)
+                    .uri("/anything")
+                    .header(header::CONTENT_ENCODING, "gzip")
+                    .body(Body::from(encoded))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
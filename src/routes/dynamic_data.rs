@@ -0,0 +1,1488 @@
+use crate::{
+    compression::Uncompressed,
+    conditional::StaticContent,
+    config,
+    echo::{bad_request, payload_too_large, BodyEcho, Echo, RequestEcho},
+    range::{self, Multipart, Selection},
+};
+use axum::{
+    body::{Bytes, StreamBody},
+    extract::{BodyStream, Path, Query, TypedHeader},
+    headers::{ContentLength, ETag, HeaderMapExt},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use base64::{
+    alphabet,
+    engine::{
+        general_purpose::{STANDARD, STANDARD_NO_PAD, URL_SAFE, URL_SAFE_NO_PAD},
+        DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig,
+    },
+    Engine,
+};
+use futures_util::{stream, StreamExt};
+use rand::{Rng, RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{
+    convert::Infallible,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::time::Instant;
+use uuid::Uuid;
+
+/// Decodes with or without trailing `=` padding.
+const LENIENT: GeneralPurposeConfig =
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent);
+const LENIENT_STANDARD: GeneralPurpose = GeneralPurpose::new(&alphabet::STANDARD, LENIENT);
+const LENIENT_URL_SAFE: GeneralPurpose = GeneralPurpose::new(&alphabet::URL_SAFE, LENIENT);
+
+const DEFAULT_CHUNK_SIZE: usize = 10 * 1024;
+const MAX_CHUNK_SIZE: usize = 1024 * 1024;
+const MAX_STREAM_LINES: usize = 100;
+const MAX_DRIP_BYTES: u64 = 10 * 1024 * 1024;
+const MAX_LINKS: u64 = 200;
+const MAX_RANGE_BYTES: u64 = 100 * 1024;
+/// Contains characters that never appear in `/range` bodies, which are all lowercase letters.
+const BYTERANGES_BOUNDARY: &str = "httpbin-byteranges-0123456789";
+const MAX_IDS: usize = 1000;
+const CROCKFORD_BASE32: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+const BASE62: &[u8; 62] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+const NANOID_ALPHABET: &[u8; 64] =
+    b"_-0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+/// KSUID timestamps count seconds from 2014-05-13T16:53:20Z rather than the Unix epoch.
+const KSUID_EPOCH: u64 = 1_400_000_000;
+/// Roughly a 128 kbit/s link.
+const DEFAULT_RATE: u64 = 16 * 1024;
+
+pub fn routes() -> Router {
+    Router::new()
+        .route("/base64", post(encode_base64))
+        .route("/base64/*value", get(decode_base64))
+        .route("/bytes/:n", get(bytes))
+        .route(
+            "/delay/:delay",
+            get(delay).post(delay).put(delay).patch(delay).delete(delay),
+        )
+        .route("/drip", get(drip))
+        .route("/links/:n", get(links_redirect))
+        .route("/links/:n/:offset", get(links))
+        .route("/range/:numbytes", get(range_bytes))
+        .route("/stream-bytes/:n", get(stream_bytes))
+        .route("/stream/:n", get(stream_json))
+        .route("/throttle/:n", get(throttle))
+        .route("/upload", post(upload))
+        .route("/uuid", get(uuid))
+}
+
+#[derive(Deserialize)]
+struct DecodeParams {
+    content_type: Option<String>,
+}
+
+/// Decodes `value` as the response body. Both the standard and URL-safe alphabets are accepted,
+/// with or without padding, and the standard alphabet's `/` may appear unescaped in the path.
+async fn decode_base64(Path(value): Path<String>, Query(params): Query<DecodeParams>) -> Response {
+    let content_type = match params.content_type.as_deref() {
+        Some(content_type) => match content_type
+            .parse::<mime::Mime>()
+            .ok()
+            .and_then(|_| HeaderValue::from_str(content_type).ok())
+        {
+            Some(content_type) => content_type,
+            None => {
+                return bad_request(&format!(
+                    "Invalid content_type \"{}\", expected a media type such as image/png",
+                    content_type
+                ))
+            }
+        },
+        None => HeaderValue::from_static(mime::TEXT_HTML_UTF_8.as_ref()),
+    };
+
+    let (engine, alphabet) = if value.contains(['-', '_']) {
+        (&LENIENT_URL_SAFE, "URL-safe")
+    } else {
+        (&LENIENT_STANDARD, "standard")
+    };
+    match engine.decode(&value) {
+        Ok(decoded) => ([(header::CONTENT_TYPE, content_type)], decoded).into_response(),
+        Err(error) => bad_request(&format!(
+            "Invalid base64 in the {} alphabet: {}",
+            alphabet, error
+        )),
+    }
+}
+
+#[derive(Deserialize)]
+struct EncodeParams {
+    #[serde(default)]
+    url_safe: bool,
+    #[serde(default = "default_padding")]
+    padding: bool,
+}
+
+fn default_padding() -> bool {
+    true
+}
+
+/// Encodes the request body, in the URL-safe alphabet and without padding if asked to.
+async fn encode_base64(Query(params): Query<EncodeParams>, body: Bytes) -> Response {
+    let encoded = match (params.url_safe, params.padding) {
+        (false, true) => STANDARD.encode(&body),
+        (false, false) => STANDARD_NO_PAD.encode(&body),
+        (true, true) => URL_SAFE.encode(&body),
+        (true, false) => URL_SAFE_NO_PAD.encode(&body),
+    };
+    ([(header::CONTENT_TYPE, mime::TEXT_PLAIN.as_ref())], encoded).into_response()
+}
+
+/// A reproducible sequence of random bytes.
+///
+/// Bytes are drawn from ChaCha8 four at a time, so the same seed yields the same sequence
+/// however it is split into chunks, and the algorithm does not change between `rand` releases.
+struct RandomBytes {
+    rng: ChaCha8Rng,
+    remaining: u64,
+    /// Bytes left over from the last word drawn.
+    spare: Vec<u8>,
+}
+
+impl RandomBytes {
+    fn new(seed: u64, len: u64) -> Self {
+        Self {
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            remaining: len,
+            spare: vec![],
+        }
+    }
+
+    fn next_chunk(&mut self, max_len: usize) -> Option<Bytes> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let len = self.remaining.min(max_len as u64) as usize;
+        self.remaining -= len as u64;
+
+        let mut chunk: Vec<u8> = self.spare.drain(..len.min(self.spare.len())).collect();
+        let missing = len - chunk.len();
+        if missing > 0 {
+            let mut drawn = vec![0; missing.next_multiple_of(4)];
+            self.rng.fill_bytes(&mut drawn);
+            self.spare = drawn.split_off(missing);
+            chunk.extend_from_slice(&drawn);
+        }
+        Some(chunk.into())
+    }
+}
+
+#[derive(Deserialize)]
+struct BytesParams {
+    seed: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct StreamBytesParams {
+    seed: Option<u64>,
+    chunk_size: Option<usize>,
+}
+
+/// Responds with `n` random bytes, reproducible by passing the `seed` echoed in `X-Seed`.
+async fn bytes(Path(n): Path<u64>, Query(params): Query<BytesParams>) -> Response {
+    let max = config::get().max_bytes;
+    if n > max {
+        return bad_request(&format!("n must be at most {}", max));
+    }
+
+    let seed = params.seed.unwrap_or_else(|| rand::thread_rng().gen());
+    let body = RandomBytes::new(seed, n)
+        .next_chunk(n as usize)
+        .unwrap_or_default();
+    random_response(seed, body)
+}
+
+/// Streams `n` random bytes in chunks of `chunk_size`, producing the same bytes as `/bytes/{n}`
+/// for the same `seed`.
+async fn stream_bytes(Path(n): Path<u64>, Query(params): Query<StreamBytesParams>) -> Response {
+    let max = config::get().max_stream_bytes;
+    if n > max {
+        return bad_request(&format!("n must be at most {}", max));
+    }
+    let chunk_size = params.chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE);
+    if chunk_size == 0 || chunk_size > MAX_CHUNK_SIZE {
+        return bad_request(&format!(
+            "chunk_size must be between 1 and {}",
+            MAX_CHUNK_SIZE
+        ));
+    }
+
+    let seed = params.seed.unwrap_or_else(|| rand::thread_rng().gen());
+    let chunks = stream::unfold(RandomBytes::new(seed, n), move |mut random| async move {
+        random
+            .next_chunk(chunk_size)
+            .map(|chunk| (Ok::<_, Infallible>(chunk), random))
+    });
+    random_response(seed, StreamBody::new(chunks))
+}
+
+// Random data does not compress, and `/bytes` clients check the exact `Content-Length`.
+fn random_response(seed: u64, body: impl IntoResponse) -> Response {
+    let mut response = (
+        [
+            (
+                header::CONTENT_TYPE,
+                HeaderValue::from_static(mime::APPLICATION_OCTET_STREAM.as_ref()),
+            ),
+            (
+                header::HeaderName::from_static("x-seed"),
+                HeaderValue::from(seed),
+            ),
+        ],
+        body,
+    )
+        .into_response();
+    response.extensions_mut().insert(Uncompressed);
+    response
+}
+
+#[derive(Deserialize)]
+struct ThrottleParams {
+    rate: Option<u64>,
+    jitter: Option<f64>,
+    seed: Option<u64>,
+    chunk_size: Option<usize>,
+}
+
+/// Streams `n` random bytes at an average of `rate` bytes per second. With `jitter`, each
+/// chunk's share of the time is scaled by a random factor in `1 ± jitter`, drawn from the same
+/// seed as the bytes, so a seed also reproduces the timing.
+async fn throttle(Path(n): Path<u64>, Query(params): Query<ThrottleParams>) -> Response {
+    let max = config::get().max_stream_bytes;
+    if n > max {
+        return bad_request(&format!("n must be at most {}", max));
+    }
+    let rate = params.rate.unwrap_or(DEFAULT_RATE);
+    if rate == 0 {
+        return bad_request("rate must be at least 1 byte per second");
+    }
+    let jitter = params.jitter.unwrap_or_default();
+    if !(0.0..=1.0).contains(&jitter) {
+        return bad_request("jitter must be between 0 and 1");
+    }
+    // By default a chunk is about a tenth of a second's worth, so the rate looks smooth.
+    let chunk_size = params
+        .chunk_size
+        .unwrap_or_else(|| (rate / 10).clamp(1, DEFAULT_CHUNK_SIZE as u64) as usize);
+    if chunk_size == 0 || chunk_size > MAX_CHUNK_SIZE {
+        return bad_request(&format!(
+            "chunk_size must be between 1 and {}",
+            MAX_CHUNK_SIZE
+        ));
+    }
+
+    let seed = params.seed.unwrap_or_else(|| rand::thread_rng().gen());
+    let mut timing = ChaCha8Rng::seed_from_u64(seed);
+    timing.set_stream(1);
+    let state = (RandomBytes::new(seed, n), timing, Instant::now());
+    let chunks = stream::unfold(state, move |(mut random, mut timing, due)| async move {
+        tokio::time::sleep_until(due).await;
+        let chunk = random.next_chunk(chunk_size)?;
+        let factor = 1.0 + jitter * timing.gen_range(-1.0..=1.0);
+        let share = Duration::from_secs_f64(chunk.len() as f64 / rate as f64 * factor);
+        Some((Ok::<_, Infallible>(chunk), (random, timing, due + share)))
+    });
+
+    let mut response = random_response(seed, StreamBody::new(chunks));
+    response
+        .headers_mut()
+        .insert(header::CONTENT_LENGTH, HeaderValue::from(n));
+    response
+}
+
+#[derive(Deserialize)]
+struct UploadParams {
+    rate: Option<u64>,
+}
+
+/// Reads the request body no faster than `rate` bytes per second, leaving the rest in the
+/// connection's buffers so the client sees backpressure, and reports what arrived.
+///
+/// The body is read as hyper delivers it, so the pause comes after each chunk rather than
+/// between individual bytes. Bodies larger than `HTTPBIN_MAX_DECOMPRESSED_SIZE` are refused with
+/// `413`, up front if `Content-Length` announces it.
+async fn upload(
+    Query(params): Query<UploadParams>,
+    content_length: Option<TypedHeader<ContentLength>>,
+    mut body: BodyStream,
+) -> Response {
+    let rate = params.rate.unwrap_or(DEFAULT_RATE);
+    if rate == 0 {
+        return bad_request("rate must be at least 1 byte per second");
+    }
+    let limit = config::get().max_decompressed_size;
+    let too_large = || payload_too_large(&format!("Uploads must be at most {} bytes", limit));
+    if content_length.is_some_and(|TypedHeader(ContentLength(length))| length > limit) {
+        return too_large();
+    }
+
+    let start = Instant::now();
+    let (mut received, mut chunks) = (0u64, 0u64);
+    while let Some(chunk) = body.next().await {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(error) => return bad_request(&format!("Failed to read body: {}", error)),
+        };
+        received += chunk.len() as u64;
+        if received > limit {
+            return too_large();
+        }
+        chunks += 1;
+        tokio::time::sleep_until(start + Duration::from_secs_f64(received as f64 / rate as f64))
+            .await;
+    }
+
+    Json(json!({
+        "bytes": received,
+        "chunks": chunks,
+        "elapsed": start.elapsed().as_secs_f64(),
+        "rate": rate,
+    }))
+    .into_response()
+}
+
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+enum Framing {
+    /// One object per line.
+    #[default]
+    Lines,
+    /// RFC 7464 text sequences, with each record introduced by an ASCII record separator.
+    JsonSeq,
+}
+
+#[derive(Deserialize)]
+struct StreamParams {
+    delay: Option<f64>,
+    #[serde(default)]
+    framing: Framing,
+}
+
+/// Streams `min(n, 100)` copies of the request echo, each with an `id` and sent as its own
+/// chunk, optionally `delay` seconds apart.
+async fn stream_json(
+    Path(n): Path<usize>,
+    Query(params): Query<StreamParams>,
+    echo: RequestEcho,
+) -> Response {
+    let delay = match config::parse_duration("delay", params.delay.unwrap_or_default()) {
+        Ok(delay) => delay,
+        Err(message) => return bad_request(&message),
+    };
+    let echo = serde_json::to_value(echo).expect("echoes always serialize");
+    let framing = params.framing;
+
+    let records = stream::unfold(0, move |id| {
+        let mut record = echo.clone();
+        async move {
+            if id == n.min(MAX_STREAM_LINES) {
+                return None;
+            }
+            if id > 0 {
+                tokio::time::sleep(delay).await;
+            }
+            if let Value::Object(fields) = &mut record {
+                fields.insert("id".to_owned(), id.into());
+            }
+            let mut line = serde_json::to_vec(&record).expect("echoes always serialize");
+            line.push(b'\n');
+            if framing == Framing::JsonSeq {
+                line.insert(0, 0x1e);
+            }
+            Some((Ok::<_, Infallible>(Bytes::from(line)), id + 1))
+        }
+    });
+
+    let content_type = match framing {
+        Framing::Lines => mime::APPLICATION_JSON.as_ref(),
+        Framing::JsonSeq => "application/json-seq",
+    };
+    let mut response = (
+        [(header::CONTENT_TYPE, content_type)],
+        StreamBody::new(records),
+    )
+        .into_response();
+    // An encoder would hold records back until its buffer fills.
+    response.extensions_mut().insert(Uncompressed);
+    response
+}
+
+/// Waits `delay` seconds, clamped to `HTTPBIN_MAX_DELAY` as httpbin does, before echoing the
+/// request. The wait is part of the handler future, so it is cancelled along with the
+/// connection if the client goes away.
+async fn delay(
+    Path(delay): Path<f64>,
+    request: RequestEcho,
+    body: BodyEcho,
+) -> Result<Json<Echo>, Response> {
+    let max = config::get().max_delay;
+    // Unlike `f64::min`, this leaves NaN for `parse_duration` to reject.
+    let delay = if delay > max { max } else { delay };
+    let delay = config::parse_duration("delay", delay).map_err(|message| bad_request(&message))?;
+    tokio::time::sleep(delay).await;
+    Ok(Json(Echo { request, body }))
+}
+
+#[derive(Deserialize)]
+struct DripParams {
+    duration: Option<f64>,
+    numbytes: Option<u64>,
+    code: Option<u16>,
+    delay: Option<f64>,
+}
+
+/// Waits `delay` seconds before responding with `code`, then sends `numbytes` asterisks one at
+/// a time, spaced evenly over `duration` seconds.
+async fn drip(Query(params): Query<DripParams>) -> Response {
+    let numbytes = params.numbytes.unwrap_or(10);
+    if numbytes == 0 || numbytes > MAX_DRIP_BYTES {
+        return bad_request(&format!(
+            "numbytes must be between 1 and {}",
+            MAX_DRIP_BYTES
+        ));
+    }
+    // Informational responses and 204, 205 and 304 cannot carry the dripped body.
+    let code = match params.code.unwrap_or(200) {
+        code @ 200..=599 if ![204, 205, 304].contains(&code) => {
+            StatusCode::from_u16(code).expect("checked above")
+        }
+        _ => {
+            return bad_request(
+                "code must be between 200 and 599, and allow a body unlike 204, 205 and 304",
+            )
+        }
+    };
+    let (duration, delay) = match (
+        config::parse_duration("duration", params.duration.unwrap_or(2.0)),
+        config::parse_duration("delay", params.delay.unwrap_or(2.0)),
+    ) {
+        (Ok(duration), Ok(delay)) => (duration, delay),
+        (Err(message), _) | (_, Err(message)) => return bad_request(&message),
+    };
+
+    tokio::time::sleep(delay).await;
+
+    // Each byte is scheduled against the start time, so slow polls do not push later bytes back.
+    let start = Instant::now();
+    let pause = duration.div_f64(numbytes as f64);
+    let bytes = stream::unfold(0, move |sent| async move {
+        tokio::time::sleep_until(start + pause.mul_f64(sent as f64)).await;
+        // The body ends one pause after the last byte, so it takes `duration` in total.
+        (sent < numbytes).then(|| (Ok::<_, Infallible>(Bytes::from_static(b"*")), sent + 1))
+    });
+
+    let mut response = (
+        code,
+        [
+            (
+                header::CONTENT_TYPE,
+                HeaderValue::from_static(mime::APPLICATION_OCTET_STREAM.as_ref()),
+            ),
+            (header::CONTENT_LENGTH, HeaderValue::from(numbytes)),
+        ],
+        StreamBody::new(bytes),
+    )
+        .into_response();
+    response.extensions_mut().insert(Uncompressed);
+    response
+}
+
+/// Redirects to the first of the `/links/{n}/{offset}` pages.
+async fn links_redirect(Path(n): Path<u64>) -> Response {
+    (
+        StatusCode::FOUND,
+        [(header::LOCATION, format!("/links/{}/0", n))],
+    )
+        .into_response()
+}
+
+/// Lists `n` links, clamped to between 1 and 200 as httpbin does, to the pages with the same `n`
+/// and each offset. The page at `offset` is named but not linked.
+async fn links(Path((n, offset)): Path<(u64, u64)>, headers: HeaderMap) -> Response {
+    let n = n.clamp(1, MAX_LINKS);
+    let mut page = String::from("<html><head><title>Links</title></head><body>");
+    for sibling in 0..n {
+        if sibling == offset {
+            page.push_str(&format!("{} ", sibling));
+        } else {
+            page.push_str(&format!(
+                "<a href='/links/{}/{}'>{}</a> ",
+                n, sibling, sibling
+            ));
+        }
+    }
+    page.push_str("</body></html>");
+    StaticContent::new(mime::TEXT_HTML_UTF_8.as_ref(), page).respond(&headers)
+}
+
+#[derive(Deserialize)]
+struct RangeParams {
+    chunk_size: Option<usize>,
+    duration: Option<f64>,
+}
+
+/// Serves `numbytes` bytes of the repeating alphabet under the strong validator
+/// `"range{numbytes}"`, honouring `Range` and `If-Range`. Whatever is selected is sent
+/// `chunk_size` bytes at a time, spread evenly over `duration` seconds.
+async fn range_bytes(
+    Path(numbytes): Path<u64>,
+    Query(params): Query<RangeParams>,
+    headers: HeaderMap,
+) -> Response {
+    if numbytes == 0 || numbytes > MAX_RANGE_BYTES {
+        return bad_request(&format!(
+            "numbytes must be between 1 and {}",
+            MAX_RANGE_BYTES
+        ));
+    }
+    let chunk_size = params.chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE);
+    if chunk_size == 0 || chunk_size > MAX_CHUNK_SIZE {
+        return bad_request(&format!(
+            "chunk_size must be between 1 and {}",
+            MAX_CHUNK_SIZE
+        ));
+    }
+    let duration = match config::parse_duration("duration", params.duration.unwrap_or_default()) {
+        Ok(duration) => duration,
+        Err(message) => return bad_request(&message),
+    };
+
+    let etag: ETag = format!("\"range{}\"", numbytes)
+        .parse()
+        .expect("range entity tags are valid");
+    let representation: Bytes = (0..numbytes)
+        .map(|offset| b'a' + (offset % 26) as u8)
+        .collect::<Vec<_>>()
+        .into();
+    let octet_stream = mime::APPLICATION_OCTET_STREAM.as_ref();
+    // The representation never changes, so only an entity tag can validate it; there is no
+    // `Last-Modified` date for `If-Range` to match.
+    let selection = range::select(
+        &headers,
+        &etag,
+        None,
+        &representation,
+        Some(Multipart {
+            boundary: BYTERANGES_BOUNDARY,
+            content_type: octet_stream,
+        }),
+    );
+    if selection.status == StatusCode::RANGE_NOT_SATISFIABLE {
+        let mut response = (
+            StatusCode::RANGE_NOT_SATISFIABLE,
+            [
+                (
+                    header::CONTENT_RANGE,
+                    selection.content_range.unwrap_or_default(),
+                ),
+                (header::ACCEPT_RANGES, "bytes".to_owned()),
+            ],
+        )
+            .into_response();
+        response.headers_mut().typed_insert(etag);
+        return response;
+    }
+    let Selection {
+        status,
+        content_range,
+        multipart_type,
+        body,
+    } = selection;
+    let content_type = multipart_type.unwrap_or_else(|| octet_stream.to_owned());
+
+    let length = body.len();
+    let chunks = stream::unfold((0, Instant::now()), move |(offset, due)| {
+        let body = body.clone();
+        async move {
+            tokio::time::sleep_until(due).await;
+            if offset == length {
+                return None;
+            }
+            let end = length.min(offset + chunk_size);
+            let share = duration.mul_f64((end - offset) as f64 / length as f64);
+            Some((
+                Ok::<_, Infallible>(body.slice(offset..end)),
+                (end, due + share),
+            ))
+        }
+    });
+
+    let mut response = (
+        status,
+        [
+            (header::CONTENT_TYPE, content_type),
+            (header::CONTENT_LENGTH, length.to_string()),
+            (header::ACCEPT_RANGES, "bytes".to_owned()),
+        ],
+        StreamBody::new(chunks),
+    )
+        .into_response();
+    let response_headers = response.headers_mut();
+    response_headers.typed_insert(etag);
+    if let Some(content_range) = content_range {
+        response_headers.insert(
+            header::CONTENT_RANGE,
+            HeaderValue::from_str(&content_range).expect("content ranges are valid headers"),
+        );
+    }
+    // Byte ranges only make sense against the unencoded representation, and the chunks are paced.
+    response.extensions_mut().insert(Uncompressed);
+    response
+}
+
+/// A kind of unique identifier that `/uuid` can generate.
+#[derive(Deserialize, Default, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum IdKind {
+    /// A time-based UUID with a random node ID.
+    V1,
+    #[default]
+    V4,
+    /// Version 1 with the timestamp reordered so the UUIDs sort by time.
+    V6,
+    /// A Unix millisecond timestamp followed by random bits.
+    V7,
+    Ulid,
+    Ksuid,
+    Nanoid,
+}
+
+impl IdKind {
+    /// The key the identifiers are returned under, which is `uuid` for every UUID version to
+    /// stay compatible with httpbin.
+    fn key(self) -> &'static str {
+        match self {
+            IdKind::V1 | IdKind::V4 | IdKind::V6 | IdKind::V7 => "uuid",
+            IdKind::Ulid => "ulid",
+            IdKind::Ksuid => "ksuid",
+            IdKind::Nanoid => "nanoid",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct UuidParams {
+    #[serde(default)]
+    kind: IdKind,
+    count: Option<usize>,
+}
+
+/// Returns a single identifier of the requested kind, or with `count`, a list of that many
+/// under the plural key.
+async fn uuid(Query(params): Query<UuidParams>) -> Response {
+    let kind = params.kind;
+    let Some(count) = params.count else {
+        return Json(json!({ kind.key(): generate_ids(kind, 1).remove(0) })).into_response();
+    };
+    if count == 0 || count > MAX_IDS {
+        return bad_request(&format!("count must be between 1 and {}", MAX_IDS));
+    }
+    Json(json!({ format!("{}s", kind.key()): generate_ids(kind, count) })).into_response()
+}
+
+/// Generates `count` identifiers. Time-ordered kinds stay in order within a batch, even when
+/// several share a timestamp.
+fn generate_ids(kind: IdKind, count: usize) -> Vec<String> {
+    let mut rng = rand::thread_rng();
+    // A random node ID must have the multicast bit set so it cannot clash with a real MAC
+    // address (RFC 9562 section 6.10).
+    let mut node_id: [u8; 6] = rng.gen();
+    node_id[0] |= 0x01;
+    let mut last_ulid: Option<u128> = None;
+
+    (0..count)
+        .map(|_| match kind {
+            IdKind::V1 => Uuid::now_v1(&node_id).to_string(),
+            IdKind::V4 => Uuid::new_v4().to_string(),
+            IdKind::V6 => Uuid::now_v6(&node_id).to_string(),
+            IdKind::V7 => Uuid::now_v7().to_string(),
+            IdKind::Ulid => {
+                let millis = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .expect("the clock is after 1970")
+                    .as_millis();
+                // Within the same millisecond, the random part is incremented instead of
+                // redrawn, as the ULID spec describes for monotonic generators.
+                let ulid = match last_ulid {
+                    Some(last) if last >> 80 == millis => last + 1,
+                    _ => millis << 80 | rng.gen::<u128>() >> 48,
+                };
+                last_ulid = Some(ulid);
+                encode_ulid(ulid)
+            }
+            IdKind::Ksuid => {
+                let seconds = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .expect("the clock is after 1970")
+                    .as_secs();
+                let mut bytes = ((seconds - KSUID_EPOCH) as u32).to_be_bytes().to_vec();
+                bytes.extend_from_slice(&rng.gen::<[u8; 16]>());
+                encode_ksuid(&bytes)
+            }
+            IdKind::Nanoid => (0..21)
+                .map(|_| char::from(NANOID_ALPHABET[rng.gen_range(0..64)]))
+                .collect(),
+        })
+        .collect()
+}
+
+/// Writes a ULID's 128 bits as 26 Crockford base32 digits, the first of which holds 3 bits.
+fn encode_ulid(ulid: u128) -> String {
+    (0..26)
+        .map(|digit| char::from(CROCKFORD_BASE32[(ulid >> (125 - 5 * digit)) as usize & 31]))
+        .collect()
+}
+
+/// Writes a KSUID's 20 bytes as a big-endian base62 number, padded to 27 digits.
+fn encode_ksuid(bytes: &[u8]) -> String {
+    let mut number = bytes.to_vec();
+    let mut digits = vec![];
+    while number.iter().any(|byte| *byte != 0) {
+        // Divides the whole number by 62, keeping the remainder as the next lowest digit.
+        let mut remainder = 0;
+        for byte in number.iter_mut() {
+            let value = remainder * 256 + u32::from(*byte);
+            *byte = (value / 62) as u8;
+            remainder = value % 62;
+        }
+        digits.push(BASE62[remainder as usize]);
+    }
+    digits.resize(27, b'0');
+    digits
+        .iter()
+        .rev()
+        .map(|digit| char::from(*digit))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::{Body, HttpBody},
+        extract::connect_info::MockConnectInfo,
+        http::Request,
+    };
+    use std::net::SocketAddr;
+    use tower::ServiceExt;
+
+    async fn send(request: Request<Body>) -> (StatusCode, Option<HeaderValue>, Bytes) {
+        let response = routes().oneshot(request).await.unwrap();
+        let status = response.status();
+        let content_type = response.headers().get(header::CONTENT_TYPE).cloned();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        (status, content_type, body)
+    }
+
+    async fn get(uri: &str) -> (StatusCode, Option<HeaderValue>, Bytes) {
+        send(Request::builder().uri(uri).body(Body::empty()).unwrap()).await
+    }
+
+    #[tokio::test]
+    async fn decodes_base64() {
+        let (status, content_type, body) = get("/base64/SFRUUEJJTiBpcyBhd2Vzb21l").await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            content_type,
+            Some(HeaderValue::from_static(mime::TEXT_HTML_UTF_8.as_ref()))
+        );
+        assert_eq!(body, "HTTPBIN is awesome");
+    }
+
+    #[tokio::test]
+    async fn decodes_both_alphabets_with_or_without_padding() {
+        // 0xfb 0xff encodes as "+/8=" in the standard alphabet and "-_8=" in the URL-safe one.
+        for value in ["+/8=", "+/8", "-_8=", "-_8"] {
+            let (status, _, body) = get(&format!("/base64/{}", value)).await;
+
+            assert_eq!(status, StatusCode::OK, "{}", value);
+            assert_eq!(&body[..], [0xfb, 0xff], "{}", value);
+        }
+    }
+
+    #[tokio::test]
+    async fn sets_requested_content_type() {
+        let (status, content_type, _) = get("/base64/e30?content_type=application/json").await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            content_type,
+            Some(HeaderValue::from_static(mime::APPLICATION_JSON.as_ref()))
+        );
+
+        let (status, _, _) = get("/base64/e30?content_type=json").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_base64() {
+        for value in ["not*base64", "-_+/", "A"] {
+            let (status, content_type, body) = get(&format!("/base64/{}", value)).await;
+
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{}", value);
+            assert_eq!(
+                content_type,
+                Some(HeaderValue::from_static(mime::TEXT_PLAIN.as_ref()))
+            );
+            assert!(body.starts_with(b"Invalid base64"), "{}", value);
+        }
+    }
+
+    #[tokio::test]
+    async fn encodes_request_body() {
+        let encode = |uri: &str| {
+            Request::builder()
+                .method("POST")
+                .uri(uri)
+                .body(Body::from(&[0xfb, 0xff][..]))
+                .unwrap()
+        };
+
+        assert_eq!(send(encode("/base64")).await.2, "+/8=");
+        assert_eq!(
+            send(encode("/base64?url_safe=true&padding=false")).await.2,
+            "-_8"
+        );
+    }
+
+    #[tokio::test]
+    async fn bytes_are_reproducible() {
+        let (status, content_type, first) = get("/bytes/1000?seed=42").await;
+        let (_, _, second) = get("/bytes/1000?seed=42").await;
+        let (_, _, other) = get("/bytes/1000?seed=43").await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            content_type,
+            Some(HeaderValue::from_static(
+                mime::APPLICATION_OCTET_STREAM.as_ref()
+            ))
+        );
+        assert_eq!(first.len(), 1000);
+        assert_eq!(first, second);
+        assert_ne!(first, other);
+    }
+
+    #[tokio::test]
+    async fn reports_generated_seed() {
+        let response = routes()
+            .oneshot(
+                Request::builder()
+                    .uri("/bytes/16")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let seed = response.headers()["x-seed"].to_str().unwrap().to_owned();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+
+        let (_, _, replayed) = get(&format!("/bytes/16?seed={}", seed)).await;
+        assert_eq!(body, replayed);
+    }
+
+    #[tokio::test]
+    async fn stream_bytes_match_bytes_for_any_chunk_size() {
+        let (_, _, expected) = get("/bytes/1001?seed=7").await;
+
+        for chunk_size in [1, 3, 10, 1024] {
+            let response = routes()
+                .oneshot(
+                    Request::builder()
+                        .uri(format!(
+                            "/stream-bytes/1001?seed=7&chunk_size={}",
+                            chunk_size
+                        ))
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert!(response.headers().get(header::CONTENT_LENGTH).is_none());
+            let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+            assert_eq!(body, expected, "{}", chunk_size);
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_oversized_requests() {
+        let max = config::get().max_bytes;
+        let (status, _, _) = get(&format!("/bytes/{}", max + 1)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, _, _) = get("/stream-bytes/10?chunk_size=0").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _, _) = get("/bytes/-1").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    fn app() -> Router {
+        routes().layer(MockConnectInfo(SocketAddr::from(([10, 10, 32, 1], 59351))))
+    }
+
+    #[tokio::test]
+    async fn streams_one_record_per_chunk() {
+        let response = app()
+            .oneshot(
+                Request::builder()
+                    .uri("/stream/3")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE),
+            Some(&HeaderValue::from_static(mime::APPLICATION_JSON.as_ref()))
+        );
+        let mut body = response.into_body();
+        for id in 0..3 {
+            let chunk = body.data().await.unwrap().unwrap();
+            assert!(chunk.ends_with(b"\n"));
+            let record: Value = serde_json::from_slice(&chunk).unwrap();
+            assert_eq!(record["id"], id);
+            assert_eq!(record["origin"], "10.10.32.1");
+        }
+        assert!(body.data().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn caps_stream_at_one_hundred_records() {
+        let response = app()
+            .oneshot(
+                Request::builder()
+                    .uri("/stream/1000")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+
+        assert_eq!(body.split(|byte| *byte == b'\n').count(), 101);
+    }
+
+    #[tokio::test]
+    async fn streams_json_text_sequences_with_delay() {
+        let start = std::time::Instant::now();
+        let response = app()
+            .oneshot(
+                Request::builder()
+                    .uri("/stream/3?framing=json-seq&delay=0.05")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE),
+            Some(&HeaderValue::from_static("application/json-seq"))
+        );
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        let records: Vec<_> = body.split(|byte| *byte == 0x1e).skip(1).collect();
+        assert_eq!(records.len(), 3);
+        for record in records {
+            assert!(serde_json::from_slice::<Value>(record).is_ok());
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_delays_beyond_limit() {
+        for delay in ["-1", "1e9", "NaN"] {
+            let response = app()
+                .oneshot(
+                    Request::builder()
+                        .uri(format!("/stream/1?delay={}", delay))
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", delay);
+        }
+    }
+
+    #[tokio::test]
+    async fn delays_any_method() {
+        let start = std::time::Instant::now();
+        let response = app()
+            .oneshot(
+                Request::builder()
+                    .method("PATCH")
+                    .uri("/delay/0.1?a=1")
+                    .body(Body::from("payload"))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let echo: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(echo["method"], "PATCH");
+        assert_eq!(echo["args"]["a"], "1");
+        assert_eq!(echo["data"], "payload");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn clamps_long_delays() {
+        let max = Duration::from_secs_f64(config::get().max_delay);
+        let start = Instant::now();
+        let request = tokio::spawn(
+            app().oneshot(
+                Request::builder()
+                    .uri("/delay/3600")
+                    .body(Body::empty())
+                    .unwrap(),
+            ),
+        );
+        tokio::task::yield_now().await;
+
+        tokio::time::advance(max - Duration::from_millis(1)).await;
+        assert!(!request.is_finished());
+        let response = request.await.unwrap().unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(start.elapsed(), max);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn cancels_delays_when_dropped() {
+        let request = tokio::spawn(
+            app().oneshot(
+                Request::builder()
+                    .uri("/delay/5")
+                    .body(Body::empty())
+                    .unwrap(),
+            ),
+        );
+        tokio::task::yield_now().await;
+        tokio::time::advance(Duration::from_secs(1)).await;
+        assert!(!request.is_finished());
+
+        // hyper drops the handler future when the client disconnects, which aborting does too.
+        request.abort();
+        assert!(request.await.unwrap_err().is_cancelled());
+        // The sleep lived in the handler future, so nothing is left running to finish it.
+        assert_eq!(
+            tokio::runtime::Handle::current()
+                .metrics()
+                .num_alive_tasks(),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_delays() {
+        for delay in ["-1", "NaN", "soon"] {
+            let response = app()
+                .oneshot(
+                    Request::builder()
+                        .uri(format!("/delay/{}", delay))
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", delay);
+        }
+    }
+
+    #[tokio::test]
+    async fn drips_bytes_evenly() {
+        let start = std::time::Instant::now();
+        let response = routes()
+            .oneshot(
+                Request::builder()
+                    .uri("/drip?numbytes=4&duration=0.2&delay=0.1&code=201")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(
+            response.headers().get(header::CONTENT_LENGTH),
+            Some(&HeaderValue::from(4))
+        );
+
+        let mut body = response.into_body();
+        let mut arrivals = vec![];
+        while let Some(chunk) = body.data().await {
+            assert_eq!(chunk.unwrap(), "*");
+            arrivals.push(start.elapsed());
+        }
+        assert_eq!(arrivals.len(), 4);
+        for (before, after) in arrivals.iter().zip(&arrivals[1..]) {
+            assert!(*after - *before >= Duration::from_millis(40));
+        }
+        assert!(start.elapsed() >= Duration::from_millis(300));
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_drips() {
+        for query in ["numbytes=0", "code=1000", "duration=-1", "delay=1e9"] {
+            let response = routes()
+                .oneshot(
+                    Request::builder()
+                        .uri(format!("/drip?{}", query))
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", query);
+        }
+    }
+
+    #[tokio::test]
+    async fn drips_only_with_codes_that_allow_a_body() {
+        for (code, expected) in [
+            (100, StatusCode::BAD_REQUEST),
+            (199, StatusCode::BAD_REQUEST),
+            (204, StatusCode::BAD_REQUEST),
+            (205, StatusCode::BAD_REQUEST),
+            (304, StatusCode::BAD_REQUEST),
+            (600, StatusCode::BAD_REQUEST),
+            (206, StatusCode::PARTIAL_CONTENT),
+            (599, StatusCode::from_u16(599).unwrap()),
+        ] {
+            let uri = format!("/drip?numbytes=1&duration=0&delay=0&code={}", code);
+            assert_eq!(get(&uri).await.0, expected, "{}", code);
+        }
+    }
+
+    #[tokio::test]
+    async fn throttles_downloads() {
+        let start = std::time::Instant::now();
+        let response = routes()
+            .oneshot(
+                Request::builder()
+                    .uri("/throttle/300?rate=1000&chunk_size=100&seed=7")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(header::CONTENT_LENGTH),
+            Some(&HeaderValue::from(300))
+        );
+
+        let mut body = response.into_body();
+        let mut received = vec![];
+        while let Some(chunk) = body.data().await {
+            let chunk = chunk.unwrap();
+            assert_eq!(chunk.len(), 100);
+            // At 1000 bytes per second, every byte already sent costs a millisecond.
+            assert!(start.elapsed() >= Duration::from_millis(received.len() as u64));
+            received.extend_from_slice(&chunk);
+        }
+        assert!(start.elapsed() >= Duration::from_millis(300));
+        assert_eq!(received, get("/bytes/300?seed=7").await.2);
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_throttles() {
+        for uri in [
+            "/throttle/10?rate=0",
+            "/throttle/10?jitter=1.5",
+            "/throttle/10?chunk_size=0",
+        ] {
+            assert_eq!(get(uri).await.0, StatusCode::BAD_REQUEST, "{}", uri);
+        }
+    }
+
+    #[tokio::test]
+    async fn reads_uploads_at_the_given_rate() {
+        let start = std::time::Instant::now();
+        let (status, _, body) = send(
+            Request::builder()
+                .method("POST")
+                .uri("/upload?rate=1000")
+                .body(Body::from(vec![0; 200]))
+                .unwrap(),
+        )
+        .await;
+
+        assert!(start.elapsed() >= Duration::from_millis(200));
+        assert_eq!(status, StatusCode::OK);
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["bytes"], 200);
+        assert_eq!(body["rate"], 1000);
+        assert!(body["elapsed"].as_f64().unwrap() >= 0.2);
+    }
+
+    #[tokio::test]
+    async fn refuses_oversized_uploads() {
+        let limit = config::get().max_decompressed_size;
+        let announced = send(
+            Request::builder()
+                .method("POST")
+                .uri("/upload")
+                .header(header::CONTENT_LENGTH, limit + 1)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(announced.0, StatusCode::PAYLOAD_TOO_LARGE);
+
+        let chunk = Bytes::from(vec![0; 1024 * 1024]);
+        let chunks =
+            (0..=limit / chunk.len() as u64).map(move |_| Ok::<_, std::io::Error>(chunk.clone()));
+        let streamed = send(
+            Request::builder()
+                .method("POST")
+                .uri(format!("/upload?rate={}", u64::MAX))
+                .body(Body::wrap_stream(stream::iter(chunks)))
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(streamed.0, StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    async fn get_range(uri: &str, headers: &[(header::HeaderName, &str)]) -> Response {
+        let mut request = Request::builder().uri(uri);
+        for (name, value) in headers {
+            request = request.header(name, *value);
+        }
+        routes()
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn serves_whole_range_representation() {
+        let response = get_range("/range/30", &[]).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let headers = response.headers();
+        assert_eq!(headers[header::ETAG], "\"range30\"");
+        assert_eq!(headers[header::ACCEPT_RANGES], "bytes");
+        assert_eq!(headers[header::CONTENT_LENGTH], "30");
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(body, "abcdefghijklmnopqrstuvwxyzabcd");
+    }
+
+    #[tokio::test]
+    async fn serves_single_and_suffix_ranges() {
+        for (range, content_range, expected) in [
+            ("bytes=2-4", "bytes 2-4/30", "cde"),
+            ("bytes=-3", "bytes 27-29/30", "bcd"),
+            ("bytes=28-", "bytes 28-29/30", "cd"),
+        ] {
+            let response = get_range("/range/30", &[(header::RANGE, range)]).await;
+
+            assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT, "{}", range);
+            assert_eq!(response.headers()[header::CONTENT_RANGE], content_range);
+            let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+            assert_eq!(body, expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn serves_multiple_ranges_as_multipart() {
+        let response = get_range("/range/30", &[(header::RANGE, "bytes=0-1, -2")]).await;
+
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "multipart/byteranges; boundary=httpbin-byteranges-0123456789"
+        );
+        assert!(response.headers().get(header::CONTENT_RANGE).is_none());
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(
+            body,
+            "--httpbin-byteranges-0123456789\r\n\
+             Content-Type: application/octet-stream\r\n\
+             Content-Range: bytes 0-1/30\r\n\r\nab\r\n\
+             --httpbin-byteranges-0123456789\r\n\
+             Content-Type: application/octet-stream\r\n\
+             Content-Range: bytes 28-29/30\r\n\r\ncd\r\n\
+             --httpbin-byteranges-0123456789--\r\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn ignores_ranges_that_amplify_the_response() {
+        let response = get_range("/range/30", &[(header::RANGE, "bytes=0-, 0-, 0-")]).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn if_range_must_match_the_entity_tag() {
+        let range = (header::RANGE, "bytes=0-0");
+        let response = get_range(
+            "/range/30",
+            &[range.clone(), (header::IF_RANGE, "\"range30\"")],
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+
+        for if_range in ["\"range31\"", "Wed, 21 Oct 2015 07:28:00 GMT"] {
+            let response =
+                get_range("/range/30", &[range.clone(), (header::IF_RANGE, if_range)]).await;
+            assert_eq!(response.status(), StatusCode::OK, "{}", if_range);
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_unsatisfiable_ranges() {
+        let response = get_range("/range/30", &[(header::RANGE, "bytes=30-")]).await;
+
+        assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(response.headers()[header::CONTENT_RANGE], "bytes */30");
+    }
+
+    #[tokio::test]
+    async fn paces_range_chunks_over_the_duration() {
+        let start = std::time::Instant::now();
+        let response = get_range("/range/100?chunk_size=25&duration=0.2", &[]).await;
+
+        let mut body = response.into_body();
+        let mut chunks = 0;
+        while let Some(chunk) = body.data().await {
+            assert_eq!(chunk.unwrap().len(), 25);
+            chunks += 1;
+        }
+        assert_eq!(chunks, 4);
+        assert!(start.elapsed() >= Duration::from_millis(200));
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_range_sizes() {
+        for uri in ["/range/0", "/range/102401", "/range/10?chunk_size=0"] {
+            assert_eq!(get(uri).await.0, StatusCode::BAD_REQUEST, "{}", uri);
+        }
+    }
+
+    #[tokio::test]
+    async fn redirects_to_the_first_links_page() {
+        let response = get_range("/links/5", &[]).await;
+
+        assert_eq!(response.status(), StatusCode::FOUND);
+        assert_eq!(response.headers()[header::LOCATION], "/links/5/0");
+    }
+
+    #[tokio::test]
+    async fn links_to_every_page_but_the_current_one() {
+        let (status, content_type, body) = get("/links/3/1").await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            content_type,
+            Some(HeaderValue::from_static(mime::TEXT_HTML_UTF_8.as_ref()))
+        );
+        assert_eq!(
+            body,
+            "<html><head><title>Links</title></head><body>\
+             <a href='/links/3/0'>0</a> 1 <a href='/links/3/2'>2</a> \
+             </body></html>"
+        );
+    }
+
+    #[tokio::test]
+    async fn clamps_the_number_of_links() {
+        let (_, _, body) = get("/links/1000/0").await;
+        let body = String::from_utf8(body.to_vec()).unwrap();
+
+        assert_eq!(body.matches("<a ").count(), 199);
+        assert!(body.contains("/links/200/199"));
+    }
+
+    async fn get_json(uri: &str) -> Value {
+        let (status, _, body) = get(uri).await;
+        assert_eq!(status, StatusCode::OK, "{}", uri);
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn returns_a_v4_uuid_by_default() {
+        let body = get_json("/uuid").await;
+        let uuid = Uuid::parse_str(body["uuid"].as_str().unwrap()).unwrap();
+        assert_eq!(uuid.get_version_num(), 4);
+    }
+
+    #[tokio::test]
+    async fn generates_each_uuid_version() {
+        for version in [1, 4, 6, 7] {
+            let body = get_json(&format!("/uuid?kind=v{}&count=3", version)).await;
+            let uuids = body["uuids"].as_array().unwrap();
+
+            assert_eq!(uuids.len(), 3);
+            for uuid in uuids {
+                let uuid = Uuid::parse_str(uuid.as_str().unwrap()).unwrap();
+                assert_eq!(uuid.get_version_num(), version);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn time_ordered_ids_sort_by_creation() {
+        for (kind, key) in [("v6", "uuids"), ("v7", "uuids"), ("ulid", "ulids")] {
+            let body = get_json(&format!("/uuid?kind={}&count=100", kind)).await;
+            let ids: Vec<&str> = body[key]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|id| id.as_str().unwrap())
+                .collect();
+            assert!(ids.windows(2).all(|pair| pair[0] < pair[1]), "{}", kind);
+        }
+    }
+
+    #[tokio::test]
+    async fn generates_ulids_ksuids_and_nanoids() {
+        let ulid = get_json("/uuid?kind=ulid").await["ulid"].clone();
+        let ulid = ulid.as_str().unwrap();
+        assert_eq!(ulid.len(), 26);
+        assert!(ulid.bytes().all(|byte| CROCKFORD_BASE32.contains(&byte)));
+
+        let ksuid = get_json("/uuid?kind=ksuid").await["ksuid"].clone();
+        let ksuid = ksuid.as_str().unwrap();
+        assert_eq!(ksuid.len(), 27);
+        assert!(ksuid.bytes().all(|byte| byte.is_ascii_alphanumeric()));
+
+        let nanoid = get_json("/uuid?kind=nanoid").await["nanoid"].clone();
+        let nanoid = nanoid.as_str().unwrap();
+        assert_eq!(nanoid.len(), 21);
+        assert!(nanoid.bytes().all(|byte| NANOID_ALPHABET.contains(&byte)));
+    }
+
+    #[test]
+    fn encodes_known_ids() {
+        assert_eq!(encode_ulid(0), "00000000000000000000000000");
+        assert_eq!(encode_ulid(u128::MAX), "7ZZZZZZZZZZZZZZZZZZZZZZZZZ");
+        // The maximum KSUID, from the reference implementation.
+        assert_eq!(encode_ksuid(&[0xff; 20]), "aWgEPTl1tmebfsQzFP4bxwgy80V");
+        assert_eq!(encode_ksuid(&[0; 20]), "000000000000000000000000000");
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_id_requests() {
+        for uri in ["/uuid?count=0", "/uuid?count=1001", "/uuid?kind=v5"] {
+            assert_eq!(get(uri).await.0, StatusCode::BAD_REQUEST, "{}", uri);
+        }
+    }
+}
@@ -1,4 +1,11 @@
+pub mod anything;
+pub mod dynamic_data;
+pub mod negotiate;
 pub mod request_inspection;
 pub mod response_formats;
 pub mod root;
+pub mod site;
+pub mod sse;
 pub mod status_codes;
+pub mod trailers;
+pub mod websocket;
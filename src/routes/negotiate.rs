@@ -0,0 +1,247 @@
+use crate::{
+    charset::Charset,
+    format::Format,
+    negotiation::{self, preferred_charset, preferred_language, preferred_media_type},
+};
+use axum::{
+    http::{header, HeaderMap, HeaderValue},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use mime::Mime;
+use serde_json::json;
+
+const GREETINGS: [(&str, &str); 5] = [
+    ("en", "Hello, world!"),
+    ("de", "Hallo, Welt!"),
+    ("es", "¡Hola, mundo!"),
+    ("fr", "Bonjour, le monde !"),
+    ("ja", "こんにちは、世界！"),
+];
+
+const CHARSETS: [&str; 2] = ["utf-8", "utf-16"];
+
+pub fn routes() -> Router {
+    Router::new().route("/negotiate", get(negotiate))
+}
+
+/// Returns a greeting in the media type, language and charset picked from the client's `Accept`,
+/// `Accept-Language` and `Accept-Charset` headers, or `406 Not Acceptable` listing what the
+/// server supports when nothing the client asked for is available.
+async fn negotiate(headers: HeaderMap) -> Response {
+    let media_types: Vec<Mime> = Format::MEDIA_TYPES
+        .iter()
+        .map(|(media_type, _)| media_type.parse().expect("media types are valid"))
+        .collect();
+    let Some(media_type) = preferred_media_type(&headers, &media_types) else {
+        return not_acceptable(
+            "Client did not request a supported media type.",
+            "accept",
+            &Format::MEDIA_TYPES.map(|(media_type, _)| media_type),
+        );
+    };
+    let format = Format::MEDIA_TYPES
+        .into_iter()
+        .find(|(candidate, _)| *candidate == media_type.essence_str())
+        .map(|(_, format)| format)
+        .expect("negotiated media types are listed");
+
+    let languages = GREETINGS.map(|(language, _)| language);
+    let Some(language) = preferred_language(&headers, &languages) else {
+        return not_acceptable(
+            "Client did not request a supported language.",
+            "accept_language",
+            &languages,
+        );
+    };
+
+    // Binary formats define their own string encoding, so only text formats have a charset.
+    let charset = if format.is_text() {
+        match preferred_charset(&headers, &CHARSETS) {
+            Some(charset) => Some(charset),
+            None => {
+                return not_acceptable(
+                    "Client did not request a supported charset.",
+                    "accept_charset",
+                    &CHARSETS,
+                )
+            }
+        }
+    } else {
+        None
+    };
+
+    let greeting = GREETINGS
+        .into_iter()
+        .find(|(candidate, _)| *candidate == language)
+        .map(|(_, greeting)| greeting);
+    let body = format.render(
+        "negotiate",
+        &json!({
+            "charset": charset,
+            "greeting": greeting,
+            "language": language,
+            "media_type": media_type.essence_str(),
+        }),
+    );
+    let (content_type, body) = match charset {
+        Some("utf-16") => (
+            format!("{}; charset=utf-16", media_type),
+            Charset::Utf16LeBom.encode(&String::from_utf8(body).expect("text formats are UTF-8")),
+        ),
+        Some(charset) => (format!("{}; charset={}", media_type, charset), body),
+        None => (media_type.to_string(), body),
+    };
+
+    (
+        [
+            (header::CONTENT_TYPE, content_type),
+            (header::CONTENT_LANGUAGE, language.to_owned()),
+            (
+                header::VARY,
+                "accept, accept-language, accept-charset".to_owned(),
+            ),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+/// Adds the headers `/negotiate` depends on to a `406 Not Acceptable` response.
+fn not_acceptable(message: &str, field: &str, supported: &[&str]) -> Response {
+    let mut response = negotiation::not_acceptable(message, field, supported);
+    response.headers_mut().insert(
+        header::VARY,
+        HeaderValue::from_static("accept, accept-language, accept-charset"),
+    );
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::{Body, Bytes},
+        http::{Request, StatusCode},
+    };
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    async fn get(headers: &[(header::HeaderName, &str)]) -> (StatusCode, HeaderMap, Bytes) {
+        let mut request = Request::builder().uri("/negotiate");
+        for (name, value) in headers {
+            request = request.header(name, *value);
+        }
+        let response = routes()
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        (status, headers, body)
+    }
+
+    #[tokio::test]
+    async fn defaults_to_json_in_english() {
+        let (status, headers, body) = get(&[]).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            headers.get(header::CONTENT_TYPE),
+            Some(&HeaderValue::from_static("application/json; charset=utf-8"))
+        );
+        assert_eq!(
+            headers.get(header::CONTENT_LANGUAGE),
+            Some(&HeaderValue::from_static("en"))
+        );
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body,
+            json!({
+                "charset": "utf-8",
+                "greeting": "Hello, world!",
+                "language": "en",
+                "media_type": "application/json",
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn negotiates_binary_formats_without_a_charset() {
+        let (status, headers, body) = get(&[
+            (
+                header::ACCEPT,
+                "application/json;q=0.5, application/cbor;q=0.9, application/*;q=0.1",
+            ),
+            (header::ACCEPT_LANGUAGE, "fr-CH, fr;q=0.9, en;q=0.8"),
+            (header::ACCEPT_CHARSET, "iso-8859-1"),
+        ])
+        .await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            headers.get(header::CONTENT_TYPE),
+            Some(&HeaderValue::from_static("application/cbor"))
+        );
+        let body: Value = ciborium::from_reader(&body[..]).unwrap();
+        assert_eq!(body["greeting"], json!("Bonjour, le monde !"));
+        assert_eq!(body["charset"], Value::Null);
+    }
+
+    #[tokio::test]
+    async fn negotiates_charset_for_text_formats() {
+        let (status, headers, body) = get(&[
+            (header::ACCEPT, "text/xml"),
+            (header::ACCEPT_LANGUAGE, "ja"),
+            (header::ACCEPT_CHARSET, "utf-8;q=0.5, utf-16"),
+        ])
+        .await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            headers.get(header::CONTENT_TYPE),
+            Some(&HeaderValue::from_static("text/xml; charset=utf-16"))
+        );
+        let (text, encoding, malformed) = encoding_rs::UTF_8.decode(&body);
+        assert_eq!(encoding, encoding_rs::UTF_16LE);
+        assert!(!malformed);
+        assert!(text.contains("<greeting>こんにちは、世界！</greeting>"));
+    }
+
+    #[tokio::test]
+    async fn not_acceptable_lists_supported_values() {
+        let (status, headers, body) = get(&[(header::ACCEPT, "image/*")]).await;
+        assert_eq!(status, StatusCode::NOT_ACCEPTABLE);
+        assert_eq!(
+            headers.get(header::CONTENT_TYPE),
+            Some(&HeaderValue::from_static(mime::APPLICATION_JSON.as_ref()))
+        );
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["accept"][0], json!("application/json"));
+
+        let (status, _, body) = get(&[(header::ACCEPT_LANGUAGE, "nl")]).await;
+        assert_eq!(status, StatusCode::NOT_ACCEPTABLE);
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body["accept_language"],
+            json!(["en", "de", "es", "fr", "ja"])
+        );
+
+        let (status, _, _) = get(&[(header::ACCEPT_CHARSET, "koi8-r")]).await;
+        assert_eq!(status, StatusCode::NOT_ACCEPTABLE);
+    }
+
+    #[tokio::test]
+    async fn does_not_offer_plain_text() {
+        let (status, _, body) = get(&[(header::ACCEPT, "text/plain")]).await;
+        assert_eq!(status, StatusCode::NOT_ACCEPTABLE);
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["accept"].as_array().unwrap().len(), 8);
+        assert!(!body["accept"]
+            .as_array()
+            .unwrap()
+            .contains(&json!("text/plain")));
+    }
+}
@@ -4,11 +4,13 @@ use axum::{
     extract::{ConnectInfo, TypedHeader},
     headers::UserAgent,
     http::HeaderMap,
-    response::IntoResponse,
+    response::Response,
     routing::get,
-    Json, Router,
+    Router,
 };
-use serde_json::{json, Map, Value};
+use serde_json::json;
+
+use crate::{echo::headers_to_json, format::ResponseFormat};
 
 pub fn routes() -> Router {
     Router::new()
@@ -17,22 +19,19 @@ pub fn routes() -> Router {
         .route("/user-agent", get(user_agent))
 }
 
-async fn headers(headers: HeaderMap) -> impl IntoResponse {
-    let return_headers = Map::from_iter(headers.iter().map(|(name, value)| {
-        (
-            name.as_str().into(),
-            Value::String(value.to_str().unwrap().into()),
-        )
-    }));
-    Json(json!({ "headers": return_headers }))
+async fn headers(format: ResponseFormat, headers: HeaderMap) -> Response {
+    format.respond("headers", json!({ "headers": headers_to_json(&headers) }))
 }
 
-async fn ip(ConnectInfo(addr): ConnectInfo<SocketAddr>) -> Json<Value> {
-    Json(json!({ "origin": addr.ip().to_string() }))
+async fn ip(format: ResponseFormat, ConnectInfo(addr): ConnectInfo<SocketAddr>) -> Response {
+    format.respond("ip", json!({ "origin": addr.ip().to_string() }))
 }
 
-async fn user_agent(TypedHeader(user_agent): TypedHeader<UserAgent>) -> Json<Value> {
-    Json(json!({ "user_agent": user_agent.as_str() }))
+async fn user_agent(
+    format: ResponseFormat,
+    TypedHeader(user_agent): TypedHeader<UserAgent>,
+) -> Response {
+    format.respond("user-agent", json!({ "user_agent": user_agent.as_str() }))
 }
 
 #[cfg(test)]
@@ -43,6 +42,7 @@ mod tests {
         extract::connect_info::MockConnectInfo,
         http::{header, HeaderValue, Request, StatusCode},
     };
+    use serde_json::Value;
     use std::net::SocketAddr;
     use tower::ServiceExt;
 
@@ -69,7 +69,7 @@ mod tests {
         );
 
         let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
-        let response_json = serde_json::from_slice::<Value>(&body.to_vec()).unwrap();
+        let response_json = serde_json::from_slice::<Value>(&body).unwrap();
         let headers = Value::as_object(&response_json["headers"]).unwrap();
         assert_eq!(headers["foo"], "value-foo");
         assert_eq!(headers["bar"], "value-bar");
@@ -91,7 +91,7 @@ mod tests {
         );
 
         let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
-        let response_json = serde_json::from_slice::<Value>(&body.to_vec()).unwrap();
+        let response_json = serde_json::from_slice::<Value>(&body).unwrap();
         assert_eq!(&response_json["origin"], "10.10.32.1");
     }
 
@@ -117,7 +117,144 @@ mod tests {
         );
 
         let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
-        let response_json = serde_json::from_slice::<Value>(&body.to_vec()).unwrap();
+        let response_json = serde_json::from_slice::<Value>(&body).unwrap();
         assert_eq!(&response_json["user_agent"], "foo-bar");
     }
+
+    #[tokio::test]
+    async fn headers_in_requested_format() {
+        let response = routes()
+            .oneshot(
+                Request::builder()
+                    .uri("/headers?format=yaml")
+                    .header(header::ACCEPT, "application/cbor")
+                    .header("foo", "value-foo")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE),
+            Some(&HeaderValue::from_static("application/yaml"))
+        );
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let response_yaml = serde_yaml::from_slice::<Value>(&body).unwrap();
+        assert_eq!(response_yaml["headers"]["foo"], "value-foo");
+    }
+
+    #[tokio::test]
+    async fn ip_in_negotiated_format() {
+        let app = routes().layer(MockConnectInfo(SocketAddr::from(([10, 10, 32, 1], 59351))));
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/ip")
+                    .header(
+                        header::ACCEPT,
+                        "application/json;q=0.5, application/msgpack",
+                    )
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE),
+            Some(&HeaderValue::from_static("application/msgpack"))
+        );
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let response_msgpack = rmp_serde::from_slice::<Value>(&body).unwrap();
+        assert_eq!(&response_msgpack["origin"], "10.10.32.1");
+    }
+
+    #[tokio::test]
+    async fn user_agent_as_text() {
+        let response = routes()
+            .oneshot(
+                Request::builder()
+                    .uri("/user-agent")
+                    .header(header::ACCEPT, "text/plain")
+                    .header(header::USER_AGENT, "foo-bar")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE),
+            Some(&HeaderValue::from_static(mime::TEXT_PLAIN_UTF_8.as_ref()))
+        );
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"user_agent: foo-bar\n");
+    }
+
+    #[tokio::test]
+    async fn rejects_unknown_formats() {
+        let response = routes()
+            .oneshot(
+                Request::builder()
+                    .uri("/headers?format=html")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn json_unless_another_format_is_preferred() {
+        for accept in [
+            "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8",
+            "text/html",
+            "text/*",
+            "application/xml;q=0.5, application/json",
+        ] {
+            let app = routes().layer(MockConnectInfo(SocketAddr::from(([10, 10, 32, 1], 59351))));
+            let response = app
+                .oneshot(
+                    Request::builder()
+                        .uri("/ip")
+                        .header(header::ACCEPT, accept)
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+
+            assert_eq!(response.status(), StatusCode::OK, "{}", accept);
+            assert_eq!(
+                response.headers().get(header::CONTENT_TYPE),
+                Some(&HeaderValue::from_static(mime::APPLICATION_JSON.as_ref())),
+                "{}",
+                accept
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn listed_format_among_unsupported_types() {
+        let response = routes()
+            .oneshot(
+                Request::builder()
+                    .uri("/headers")
+                    .header(header::ACCEPT, "text/html, application/yaml, */*;q=0.1")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE),
+            Some(&HeaderValue::from_static("application/yaml"))
+        );
+    }
 }
@@ -1,34 +1,418 @@
-use axum::{response::Html, response::IntoResponse, routing::get, Router,
-  http::{StatusCode, header::{self}}};
+use crate::{
+    charset::Charset,
+    compression::Uncompressed,
+    conditional::StaticContent,
+    config,
+    content_coding::ContentCoding,
+    echo::{bad_request, RequestEcho},
+};
+use async_compression::Level;
+use axum::{
+    body::{Bytes, StreamBody},
+    extract::{Path, Query},
+    http::{header, HeaderMap},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::io::{AsyncReadExt, BufReader};
+use tokio_util::io::ReaderStream;
 
+const DENY_PAGE: &str = include_str!("../templates/deny.txt");
+const HTML_PAGE: &str = include_str!("../templates/moby.html");
+const JSON_PAGE: &str = include_str!("../templates/sample.json");
+const ROBOTS_TXT: &str = include_str!("../templates/robots.txt");
 const UTF8_PAGE: &str = include_str!("../templates/utf8.html");
 const XML_PAGE: &str = include_str!("../templates/sample.xml");
 
+const DEFAULT_BOMB_SIZE: u64 = 10 * 1024 * 1024;
+const MAX_NESTED_CODINGS: usize = 8;
+
 pub fn routes() -> Router {
-    Router::new().route("/encoding/utf8", get(utf8))
-    .route("/xml", get(xml))
+    Router::new()
+        .route("/brotli", get(brotli))
+        .route("/compressed/bomb/:coding", get(bomb))
+        .route("/compressed/mislabeled", get(mislabeled))
+        .route("/compressed/nested", get(nested))
+        .route("/compressed/truncated/:coding", get(truncated))
+        .route("/deflate", get(deflate))
+        .route("/deny", get(deny))
+        .route("/encoding/utf8", get(utf8))
+        .route("/encoding/:charset", get(encoding))
+        .route("/encoding/malformed/:kind", get(malformed))
+        .route("/gzip", get(gzip))
+        .route("/html", get(html))
+        .route("/json", get(json_page))
+        .route("/robots.txt", get(robots_txt))
+        .route("/xml", get(xml))
+        .route("/zstd", get(zstd))
+}
+
+async fn gzip(echo: RequestEcho) -> Response {
+    encoded_echo(ContentCoding::Gzip, "gzipped", echo).await
+}
+
+async fn deflate(echo: RequestEcho) -> Response {
+    encoded_echo(ContentCoding::Deflate, "deflated", echo).await
+}
+
+async fn brotli(echo: RequestEcho) -> Response {
+    encoded_echo(ContentCoding::Brotli, "brotli", echo).await
+}
+
+async fn zstd(echo: RequestEcho) -> Response {
+    encoded_echo(ContentCoding::Zstd, "zstd", echo).await
+}
+
+async fn encoded_echo(coding: ContentCoding, flag: &str, echo: RequestEcho) -> Response {
+    let mut body = json!(echo);
+    body[flag] = Value::Bool(true);
+
+    (
+        [
+            (header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref()),
+            (header::CONTENT_ENCODING, coding.as_str()),
+        ],
+        coding.encode(body.to_string().as_bytes()).await,
+    )
+        .into_response()
+}
+
+#[derive(Deserialize)]
+struct BombParams {
+    size: Option<u64>,
+}
+
+/// Streams `size` zero bytes through the encoder, so a tiny body expands enormously.
+async fn bomb(Path(coding): Path<String>, Query(params): Query<BombParams>) -> Response {
+    let coding = match parse_coding(&coding) {
+        Ok(Some(coding)) => coding,
+        Ok(None) => return bad_request("Cannot build a bomb with the identity coding"),
+        Err(message) => return bad_request(&message),
+    };
+    let size = params.size.unwrap_or(DEFAULT_BOMB_SIZE);
+    let max_size = config::get().max_bomb_size;
+    if size > max_size {
+        return bad_request(&format!("Size must be at most {} bytes", max_size));
+    }
+
+    let zeros = BufReader::with_capacity(64 * 1024, tokio::io::repeat(0).take(size));
+    (
+        [
+            (
+                header::CONTENT_TYPE,
+                mime::APPLICATION_OCTET_STREAM.to_string(),
+            ),
+            (header::CONTENT_ENCODING, coding.as_str().to_owned()),
+            (
+                header::HeaderName::from_static("x-decoded-length"),
+                size.to_string(),
+            ),
+        ],
+        StreamBody::new(ReaderStream::new(
+            coding.encoder_with_level(zeros, Level::Fastest),
+        )),
+    )
+        .into_response()
+}
+
+#[derive(Deserialize)]
+struct NestedParams {
+    codings: Option<String>,
+}
+
+/// Applies several codings in turn, listing them all in `Content-Encoding`.
+async fn nested(echo: RequestEcho, Query(params): Query<NestedParams>) -> Response {
+    let mut codings = vec![];
+    for token in params.codings.as_deref().unwrap_or("gzip,gzip").split(',') {
+        match parse_coding(token.trim()) {
+            Ok(Some(coding)) => codings.push(coding),
+            Ok(None) => {}
+            Err(message) => return bad_request(&message),
+        }
+    }
+    if codings.is_empty() || codings.len() > MAX_NESTED_CODINGS {
+        return bad_request(&format!(
+            "Between 1 and {} codings are required",
+            MAX_NESTED_CODINGS
+        ));
+    }
+
+    let names: Vec<_> = codings.iter().map(|coding| coding.as_str()).collect();
+    let mut body = json!(echo);
+    body["codings"] = json!(names);
+    let mut body = body.to_string().into_bytes();
+    for coding in &codings {
+        body = coding.encode(&body).await;
+    }
+
+    (
+        [
+            (header::CONTENT_TYPE, mime::APPLICATION_JSON.to_string()),
+            (header::CONTENT_ENCODING, names.join(", ")),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+#[derive(Deserialize)]
+struct MislabeledParams {
+    declared: Option<String>,
+    actual: Option<String>,
+}
+
+/// Encodes the body with one coding (identity by default) while declaring another.
+async fn mislabeled(echo: RequestEcho, Query(params): Query<MislabeledParams>) -> Response {
+    let declared = match parse_coding(params.declared.as_deref().unwrap_or("gzip")) {
+        Ok(declared) => declared,
+        Err(message) => return bad_request(&message),
+    };
+    let actual = match parse_coding(params.actual.as_deref().unwrap_or("identity")) {
+        Ok(actual) => actual,
+        Err(message) => return bad_request(&message),
+    };
+
+    let mut body = json!(echo);
+    body["declared"] = json!(declared.map_or("identity", ContentCoding::as_str));
+    body["actual"] = json!(actual.map_or("identity", ContentCoding::as_str));
+    let body = body.to_string().into_bytes();
+    let body = match actual {
+        Some(coding) => coding.encode(&body).await,
+        None => body,
+    };
+
+    let mut response = (
+        [(header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())],
+        body,
+    )
+        .into_response();
+    if let Some(declared) = declared {
+        response.headers_mut().insert(
+            header::CONTENT_ENCODING,
+            header::HeaderValue::from_static(declared.as_str()),
+        );
+    }
+    response.extensions_mut().insert(Uncompressed);
+    response
 }
 
-async fn xml() -> impl IntoResponse {
+#[derive(Deserialize)]
+struct TruncatedParams {
+    keep: Option<usize>,
+}
+
+/// Sends only the first `keep` bytes (half by default) of an encoded body, with a matching
+/// `Content-Length` so that the response itself completes normally.
+async fn truncated(Path(coding): Path<String>, Query(params): Query<TruncatedParams>) -> Response {
+    let coding = match parse_coding(&coding) {
+        Ok(Some(coding)) => coding,
+        Ok(None) => return bad_request("Cannot truncate the identity coding"),
+        Err(message) => return bad_request(&message),
+    };
+
+    let mut body = coding.encode(UTF8_PAGE.as_bytes()).await;
+    let encoded_length = body.len();
+    body.truncate(
+        params
+            .keep
+            .unwrap_or(encoded_length / 2)
+            .min(encoded_length - 1),
+    );
+
     (
-        StatusCode::OK,
-        [(header::CONTENT_TYPE, mime::TEXT_XML.essence_str())],
-        XML_PAGE,
+        [
+            (header::CONTENT_TYPE, mime::TEXT_HTML_UTF_8.to_string()),
+            (header::CONTENT_ENCODING, coding.as_str().to_owned()),
+            (
+                header::HeaderName::from_static("x-encoded-length"),
+                encoded_length.to_string(),
+            ),
+        ],
+        body,
     )
+        .into_response()
+}
+
+/// Parses a coding name from a path or query parameter, where `identity` means no coding.
+fn parse_coding(token: &str) -> Result<Option<ContentCoding>, String> {
+    if token.eq_ignore_ascii_case("identity") {
+        return Ok(None);
+    }
+    ContentCoding::from_token(token)
+        .map(Some)
+        .ok_or_else(|| format!("Unsupported coding \"{}\"", token))
+}
+
+async fn xml(headers: HeaderMap) -> Response {
+    StaticContent::new(mime::TEXT_XML.essence_str(), XML_PAGE).respond(&headers)
+}
+
+async fn utf8(headers: HeaderMap) -> Response {
+    StaticContent::new(mime::TEXT_HTML_UTF_8.as_ref(), UTF8_PAGE).respond(&headers)
+}
+
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum Declaration {
+    #[default]
+    Header,
+    Meta,
+    None,
+}
+
+#[derive(Deserialize)]
+struct EncodingParams {
+    #[serde(default)]
+    declare: Declaration,
+}
+
+/// Serves the UTF-8 demo page transcoded to a legacy charset, which is declared in the
+/// `Content-Type` header, in a `<meta charset>` tag, or nowhere, depending on `declare`.
+async fn encoding(
+    Path(charset): Path<String>,
+    Query(params): Query<EncodingParams>,
+    headers: HeaderMap,
+) -> Response {
+    let charset = match Charset::from_token(&charset) {
+        Some(charset) => charset,
+        None => {
+            let supported: Vec<_> = Charset::ALL
+                .iter()
+                .map(|charset| charset.as_str())
+                .collect();
+            return bad_request(&format!(
+                "Unsupported charset \"{}\", expected one of: utf8, {}",
+                charset,
+                supported.join(", ")
+            ));
+        }
+    };
+
+    let (content_type, page) = match params.declare {
+        Declaration::Header => (
+            format!("{}; charset={}", mime::TEXT_HTML, charset.label()),
+            UTF8_PAGE.to_owned(),
+        ),
+        Declaration::Meta => (
+            mime::TEXT_HTML.to_string(),
+            format!("<meta charset=\"{}\">\n{}", charset.label(), UTF8_PAGE),
+        ),
+        Declaration::None => (mime::TEXT_HTML.to_string(), UTF8_PAGE.to_owned()),
+    };
+    StaticContent::new(&content_type, charset.encode(&page)).respond(&headers)
+}
+
+const MALFORMED_KINDS: [&str; 6] = [
+    "invalid-utf8",
+    "overlong",
+    "cesu8",
+    "bom-mismatch",
+    "json-nul",
+    "json-raw-nul",
+];
+
+/// Returns the content type and body of a deliberately broken text fixture.
+fn malformed_text(kind: &str) -> Option<(&'static str, Bytes)> {
+    let plain = mime::TEXT_PLAIN_UTF_8.as_ref();
+    let json = mime::APPLICATION_JSON.as_ref();
+    let fixture = match kind {
+        "invalid-utf8" => (
+            plain,
+            Bytes::from_static(
+                b"Invalid UTF-8 sequences, each between brackets:\n\
+                lone continuation byte: [\x80]\n\
+                truncated two-byte sequence: [\xc3]\n\
+                truncated three-byte sequence: [\xe2\x82]\n\
+                truncated four-byte sequence: [\xf0\x9f\x98]\n\
+                bytes that never appear in UTF-8: [\xfe\xff]\n\
+                code point above U+10FFFF: [\xf4\x90\x80\x80]\n\
+                valid text after the errors: [caf\xc3\xa9]\n",
+            ),
+        ),
+        "overlong" => (
+            plain,
+            Bytes::from_static(
+                b"Overlong encodings, each between brackets:\n\
+                '/' in two bytes: [\xc0\xaf]\n\
+                '/' in three bytes: [\xe0\x80\xaf]\n\
+                '/' in four bytes: [\xf0\x80\x80\xaf]\n\
+                'A' in two bytes: [\xc1\x81]\n\
+                NUL in two bytes, as in Java's modified UTF-8: [\xc0\x80]\n",
+            ),
+        ),
+        "cesu8" => (
+            plain,
+            Bytes::from_static(
+                b"Surrogates encoded as CESU-8, each between brackets:\n\
+                U+1F600 as a surrogate pair: [\xed\xa0\xbd\xed\xb8\x80]\n\
+                lone high surrogate U+D800: [\xed\xa0\x80]\n\
+                lone low surrogate U+DC00: [\xed\xb0\x80]\n\
+                U+1F600 in UTF-8 for comparison: [\xf0\x9f\x98\x80]\n",
+            ),
+        ),
+        "bom-mismatch" => (
+            plain,
+            Charset::Utf16LeBom
+                .encode(
+                    "This text starts with a UTF-16LE byte order mark but is declared as UTF-8.\n",
+                )
+                .into(),
+        ),
+        // Valid JSON whose decoded string contains a NUL character.
+        "json-nul" => (
+            json,
+            Bytes::from_static(b"{\"nul\":\"before\\u0000after\"}"),
+        ),
+        // Invalid JSON, since control characters must be escaped inside strings.
+        "json-raw-nul" => (json, Bytes::from_static(b"{\"nul\":\"before\x00after\"}")),
+        _ => return None,
+    };
+    Some(fixture)
+}
+
+/// Serves text that is broken in a specific way, for testing how clients cope with bad input.
+async fn malformed(Path(kind): Path<String>, headers: HeaderMap) -> Response {
+    match malformed_text(&kind) {
+        Some((content_type, body)) => StaticContent::new(content_type, body).respond(&headers),
+        None => bad_request(&format!(
+            "Unknown fixture \"{}\", expected one of: {}",
+            kind,
+            MALFORMED_KINDS.join(", ")
+        )),
+    }
 }
 
-async fn utf8() -> Html<&'static str> {
-    UTF8_PAGE.into()
+async fn html(headers: HeaderMap) -> Response {
+    StaticContent::new(mime::TEXT_HTML_UTF_8.as_ref(), HTML_PAGE).respond(&headers)
+}
+
+async fn json_page(headers: HeaderMap) -> Response {
+    StaticContent::new(mime::APPLICATION_JSON.as_ref(), JSON_PAGE).respond(&headers)
+}
+
+async fn robots_txt(headers: HeaderMap) -> Response {
+    StaticContent::new(mime::TEXT_PLAIN.as_ref(), ROBOTS_TXT).respond(&headers)
+}
+
+async fn deny(headers: HeaderMap) -> Response {
+    StaticContent::new(mime::TEXT_PLAIN.as_ref(), DENY_PAGE).respond(&headers)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use async_compression::tokio::bufread::{BrotliDecoder, GzipDecoder, ZlibDecoder, ZstdDecoder};
     use axum::{
         body::Body,
+        extract::connect_info::MockConnectInfo,
         http::{header, HeaderValue, Request, StatusCode},
     };
-    use tower::ServiceExt;
+    use std::net::SocketAddr;
+    use tokio::io::{AsyncRead, AsyncReadExt};
+    use tower::{Service, ServiceExt};
 
     #[tokio::test]
     async fn utf8() {
@@ -78,4 +462,396 @@ mod tests {
         assert!(std::str::from_utf8(&body).is_ok())
     }
 
+    async fn get_encoding(uri: &str) -> (HeaderMap, Bytes) {
+        let response = routes()
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let headers = response.headers().clone();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        (headers, body)
+    }
+
+    #[tokio::test]
+    async fn encoding_declares_charset_in_header() {
+        let (headers, body) = get_encoding("/encoding/koi8-r").await;
+
+        assert_eq!(
+            headers.get(header::CONTENT_TYPE),
+            Some(&HeaderValue::from_static("text/html; charset=koi8-r"))
+        );
+        let (text, _, malformed) = encoding_rs::KOI8_R.decode(&body);
+        assert!(!malformed);
+        assert!(text.starts_with("<h1>Unicode Demo</h1>"));
+        // Greek is outside KOI8-R and survives as character references.
+        assert!(text.contains("&#954;"));
+    }
+
+    #[tokio::test]
+    async fn encoding_declares_charset_in_meta() {
+        let (headers, body) = get_encoding("/encoding/shift_jis?declare=meta").await;
+
+        assert_eq!(
+            headers.get(header::CONTENT_TYPE),
+            Some(&HeaderValue::from_static("text/html"))
+        );
+        assert!(body.starts_with(b"<meta charset=\"shift_jis\">\n<h1>"));
+    }
+
+    #[tokio::test]
+    async fn encoding_without_declaration() {
+        let (headers, body) = get_encoding("/encoding/utf-16be?declare=none").await;
+
+        assert_eq!(
+            headers.get(header::CONTENT_TYPE),
+            Some(&HeaderValue::from_static("text/html"))
+        );
+        assert!(body.starts_with(b"\0<\0h\x001\0>"));
+        let (text, malformed) = encoding_rs::UTF_16BE.decode_without_bom_handling(&body);
+        assert!(!malformed);
+        assert_eq!(text, UTF8_PAGE);
+    }
+
+    #[tokio::test]
+    async fn encoding_with_bom_is_labelled_utf16() {
+        let (headers, body) = get_encoding("/encoding/utf-16le-bom").await;
+
+        assert_eq!(
+            headers.get(header::CONTENT_TYPE),
+            Some(&HeaderValue::from_static("text/html; charset=utf-16"))
+        );
+        assert!(body.starts_with(b"\xff\xfe<\0"));
+    }
+
+    #[tokio::test]
+    async fn encoding_rejects_unknown_charsets() {
+        let response = routes()
+            .oneshot(
+                Request::builder()
+                    .uri("/encoding/ebcdic")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn malformed_text_is_not_utf8() {
+        for kind in ["invalid-utf8", "overlong", "cesu8", "bom-mismatch"] {
+            let (headers, body) = get_encoding(&format!("/encoding/malformed/{}", kind)).await;
+
+            assert_eq!(
+                headers.get(header::CONTENT_TYPE),
+                Some(&HeaderValue::from_static(mime::TEXT_PLAIN_UTF_8.as_ref())),
+                "{}",
+                kind
+            );
+            assert!(std::str::from_utf8(&body).is_err(), "{}", kind);
+        }
+    }
+
+    #[tokio::test]
+    async fn malformed_json_nul() {
+        let (_, body) = get_encoding("/encoding/malformed/json-nul").await;
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["nul"], json!("before\0after"));
+
+        let (_, body) = get_encoding("/encoding/malformed/json-raw-nul").await;
+        assert!(serde_json::from_slice::<Value>(&body).is_err());
+        assert!(std::str::from_utf8(&body).is_ok());
+    }
+
+    #[test]
+    fn malformed_kinds_are_all_served() {
+        for kind in MALFORMED_KINDS {
+            assert!(malformed_text(kind).is_some(), "{}", kind);
+        }
+        assert!(malformed_text("utf8").is_none());
+    }
+
+    #[tokio::test]
+    async fn json() {
+        let app = routes();
+
+        let response = app
+            .oneshot(Request::builder().uri("/json").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE),
+            Some(&HeaderValue::from_static(mime::APPLICATION_JSON.as_ref()))
+        );
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["slideshow"]["title"], json!("Sample Slide Show"));
+    }
+
+    #[tokio::test]
+    async fn html() {
+        let app = routes();
+
+        let response = app
+            .oneshot(Request::builder().uri("/html").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE),
+            Some(&HeaderValue::from_static(mime::TEXT_HTML_UTF_8.as_ref()))
+        );
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body = std::str::from_utf8(&body).unwrap();
+        assert!(body.contains("<h1>Herman Melville - Moby-Dick</h1>"));
+    }
+
+    #[tokio::test]
+    async fn robots_txt_denies_deny() {
+        let mut app = routes();
+
+        let request = Request::builder()
+            .uri("/robots.txt")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.ready().await.unwrap().call(request).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE),
+            Some(&HeaderValue::from_static(mime::TEXT_PLAIN.as_ref()))
+        );
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"User-agent: *\nDisallow: /deny\n");
+
+        let request = Request::builder().uri("/deny").body(Body::empty()).unwrap();
+        let response = app.ready().await.unwrap().call(request).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert!(std::str::from_utf8(&body)
+            .unwrap()
+            .contains("YOU SHOULDN'T BE HERE"));
+    }
+
+    #[tokio::test]
+    async fn utf8_supports_ranges() {
+        let app = routes();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/encoding/utf8")
+                    .header(header::RANGE, "bytes=0-14")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(
+            response.headers().get(header::CONTENT_RANGE),
+            Some(&HeaderValue::from_str(&format!("bytes 0-14/{}", UTF8_PAGE.len())).unwrap())
+        );
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(&body[..], &UTF8_PAGE.as_bytes()[..15]);
+    }
+
+    async fn decode(mut decoder: impl AsyncRead + Unpin) -> Value {
+        let mut decoded = vec![];
+        decoder.read_to_end(&mut decoded).await.unwrap();
+        serde_json::from_slice(&decoded).unwrap()
+    }
+
+    #[tokio::test]
+    async fn compressed_echoes() {
+        let mut app = routes().layer(MockConnectInfo(SocketAddr::from(([10, 10, 32, 1], 59351))));
+
+        for (uri, coding, flag) in [
+            ("/gzip", "gzip", "gzipped"),
+            ("/deflate", "deflate", "deflated"),
+            ("/brotli", "br", "brotli"),
+            ("/zstd", "zstd", "zstd"),
+        ] {
+            let response = app
+                .ready()
+                .await
+                .unwrap()
+                .call(
+                    Request::builder()
+                        .uri(uri)
+                        .header("foo", "value-foo")
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(
+                response.headers().get(header::CONTENT_ENCODING),
+                Some(&HeaderValue::from_static(coding))
+            );
+            assert_eq!(
+                response.headers().get(header::CONTENT_TYPE),
+                Some(&HeaderValue::from_static(mime::APPLICATION_JSON.as_ref()))
+            );
+
+            let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+            let echo = match coding {
+                "gzip" => decode(GzipDecoder::new(&body[..])).await,
+                "deflate" => decode(ZlibDecoder::new(&body[..])).await,
+                "br" => decode(BrotliDecoder::new(&body[..])).await,
+                _ => decode(ZstdDecoder::new(&body[..])).await,
+            };
+            assert_eq!(echo[flag], true);
+            assert_eq!(echo["method"], "GET");
+            assert_eq!(echo["origin"], "10.10.32.1");
+            assert_eq!(echo["headers"]["foo"], "value-foo");
+        }
+    }
+
+    #[tokio::test]
+    async fn bomb_expands_to_requested_size() {
+        let app = routes();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/compressed/bomb/zstd?size=1000000")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(header::CONTENT_ENCODING),
+            Some(&HeaderValue::from_static("zstd"))
+        );
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert!(body.len() < 1000);
+        let mut decoded = vec![];
+        ZstdDecoder::new(&body[..])
+            .read_to_end(&mut decoded)
+            .await
+            .unwrap();
+        assert_eq!(decoded.len(), 1000000);
+        assert!(decoded.iter().all(|byte| *byte == 0));
+    }
+
+    #[tokio::test]
+    async fn bomb_rejects_sizes_above_limit() {
+        let app = routes();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri(format!(
+                        "/compressed/bomb/gzip?size={}",
+                        config::get().max_bomb_size + 1
+                    ))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn nested_applies_every_coding() {
+        let app = routes().layer(MockConnectInfo(SocketAddr::from(([10, 10, 32, 1], 59351))));
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/compressed/nested?codings=gzip,br")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            response.headers().get(header::CONTENT_ENCODING),
+            Some(&HeaderValue::from_static("gzip, br"))
+        );
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let mut gzipped = vec![];
+        BrotliDecoder::new(&body[..])
+            .read_to_end(&mut gzipped)
+            .await
+            .unwrap();
+        let echo = decode(GzipDecoder::new(&gzipped[..])).await;
+        assert_eq!(echo["codings"], json!(["gzip", "br"]));
+    }
+
+    #[tokio::test]
+    async fn mislabeled_declares_a_different_coding() {
+        let app = routes().layer(MockConnectInfo(SocketAddr::from(([10, 10, 32, 1], 59351))));
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/compressed/mislabeled?declared=br")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            response.headers().get(header::CONTENT_ENCODING),
+            Some(&HeaderValue::from_static("br"))
+        );
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let echo = serde_json::from_slice::<Value>(&body).unwrap();
+        assert_eq!(echo["declared"], "br");
+        assert_eq!(echo["actual"], "identity");
+    }
+
+    #[tokio::test]
+    async fn truncated_stream_fails_to_decode() {
+        let app = routes();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/compressed/truncated/gzip")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let encoded_length: usize = response.headers()["x-encoded-length"]
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(body.len(), encoded_length / 2);
+        let mut decoded = vec![];
+        assert!(GzipDecoder::new(&body[..])
+            .read_to_end(&mut decoded)
+            .await
+            .is_err());
+    }
 }
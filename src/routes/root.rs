@@ -1,6 +1,7 @@
+use crate::conditional::StaticContent;
 use axum::{
-    http::{header, StatusCode},
-    response::{Html, IntoResponse},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
     routing::get,
     Router,
 };
@@ -20,16 +21,17 @@ pub fn routes() -> Router {
         .fallback(not_found)
 }
 
-async fn index() -> Html<String> {
+async fn index(headers: HeaderMap) -> Response {
     // It looks like templates were intended here but isn't legitimately used (yet).
-    render!(INDEX_TEMPLATE, prefix => "").into()
+    StaticContent::new(
+        mime::TEXT_HTML_UTF_8.as_ref(),
+        render!(INDEX_TEMPLATE, prefix => ""),
+    )
+    .respond(&headers)
 }
 
-async fn openapi() -> impl IntoResponse {
-    (
-        [(header::CONTENT_TYPE, "application/yaml")],
-        OPENAPI_SPECIFICATION,
-    )
+async fn openapi(headers: HeaderMap) -> Response {
+    StaticContent::new("application/yaml", OPENAPI_SPECIFICATION).respond(&headers)
 }
 
 async fn api_docs() -> impl IntoResponse {
@@ -39,8 +41,10 @@ async fn api_docs() -> impl IntoResponse {
     )
 }
 
-async fn not_found() -> impl IntoResponse {
-    (StatusCode::NOT_FOUND, Html(NOT_FOUND_PAGE))
+async fn not_found(headers: HeaderMap) -> Response {
+    StaticContent::new(mime::TEXT_HTML_UTF_8.as_ref(), NOT_FOUND_PAGE)
+        .with_status(StatusCode::NOT_FOUND)
+        .respond(&headers)
 }
 
 #[cfg(test)]
@@ -50,7 +54,7 @@ mod tests {
         body::Body,
         http::{HeaderValue, Request},
     };
-    use tower::ServiceExt;
+    use tower::{Service, ServiceExt};
 
     #[tokio::test]
     async fn index() {
@@ -88,6 +92,44 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn openapi_is_conditional() {
+        let mut app = routes();
+
+        let response = app
+            .ready()
+            .await
+            .unwrap()
+            .call(
+                Request::builder()
+                    .uri("/openapi.yaml")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let etag = response.headers().get(header::ETAG).unwrap().clone();
+        assert!(response.headers().get(header::LAST_MODIFIED).is_some());
+
+        let response = app
+            .ready()
+            .await
+            .unwrap()
+            .call(
+                Request::builder()
+                    .uri("/openapi.yaml")
+                    .header(header::IF_NONE_MATCH, etag)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert!(body.is_empty());
+    }
+
     #[tokio::test]
     async fn api_docs() {
         let app = routes();
@@ -0,0 +1,637 @@
+use crate::{
+    conditional::StaticContent,
+    echo::{bad_request, request_origin},
+};
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Path, Query},
+    http::{header, request::Parts, HeaderMap, StatusCode, Uri},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use minijinja::render;
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+const PAGE_TEMPLATE: &str = include_str!("../templates/site/page.html");
+const ROBOTS_TEMPLATE: &str = include_str!("../templates/site/robots.txt");
+const SITEMAP_TEMPLATE: &str = include_str!("../templates/site/sitemap.xml");
+const SITEMAP_INDEX_TEMPLATE: &str = include_str!("../templates/site/sitemap_index.xml");
+
+const DEFAULT_FANOUT: u64 = 3;
+const DEFAULT_DEPTH: u32 = 3;
+const MAX_FANOUT: u64 = 10;
+/// Keeps the deepest level within a single sitemap's limit of 50,000 URLs.
+const MAX_DEPTH: u32 = 4;
+const MAX_REDIRECT_HOPS: u32 = 10;
+
+const WORDS: [&str; 16] = [
+    "anchor", "breeze", "canyon", "drift", "ember", "fable", "glacier", "harbor", "island",
+    "juniper", "kelp", "lantern", "meadow", "nectar", "orchard", "pebble",
+];
+
+pub fn routes() -> Router {
+    Router::new()
+        .route("/site", get(start))
+        .route("/site/:seed/:fanout/:depth/", get(home))
+        .route(
+            "/site/:seed/:fanout/:depth/missing/:id",
+            get(soft_not_found),
+        )
+        .route("/site/:seed/:fanout/:depth/p/*path", get(page))
+        .route("/site/:seed/:fanout/:depth/private/:id", get(private))
+        .route(
+            "/site/:seed/:fanout/:depth/redirect/:hops/*target",
+            get(redirect),
+        )
+        .route("/site/:seed/:fanout/:depth/robots.txt", get(robots))
+        .route("/site/:seed/:fanout/:depth/sitemap.xml", get(sitemap_index))
+        .route("/site/:seed/:fanout/:depth/sitemap/:level", get(sitemap))
+}
+
+/// A generated website: a tree of pages `depth` levels deep below the home page, where every
+/// page that is not a leaf links to `fanout` children.
+///
+/// Everything about a page, from its text to which of its links are `nofollow` or go through a
+/// redirect chain, is drawn from a generator seeded with `seed` and the page's position, so
+/// the same URL always serves the same content.
+struct Site {
+    seed: u64,
+    fanout: u64,
+    depth: u32,
+    /// The scheme and host the client used, for the absolute URLs in sitemaps and canonical
+    /// links. Empty, leaving the URLs relative and `robots.txt` without a `Sitemap`, if the
+    /// request had no usable `Host`.
+    origin: String,
+}
+
+#[derive(Deserialize)]
+struct SiteParams {
+    seed: u64,
+    fanout: u64,
+    depth: u32,
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for Site
+where
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Path(params) = Path::<SiteParams>::from_request_parts(parts, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        Site::new(params, &parts.headers).map_err(|message| bad_request(&message))
+    }
+}
+
+/// The origin of the request, if it is a plain `http` or `https` origin that can be pasted into
+/// the templates as is. `Host` and `X-Forwarded-Proto` are client-controlled, and the templates
+/// render them unescaped into HTML and XML.
+fn site_origin(headers: &HeaderMap) -> Option<String> {
+    let origin = request_origin(headers)?;
+    let uri: Uri = origin.parse().ok()?;
+    let scheme = uri
+        .scheme_str()
+        .filter(|scheme| ["http", "https"].contains(scheme))?;
+    let authority = uri.authority()?.as_str();
+    let plain = authority
+        .bytes()
+        .all(|byte| byte.is_ascii_alphanumeric() || b"-.:[]".contains(&byte));
+    // Anything after the authority would have been parsed into the path.
+    (plain && origin == format!("{}://{}", scheme, authority)).then_some(origin)
+}
+
+impl Site {
+    fn new(params: SiteParams, headers: &HeaderMap) -> Result<Self, String> {
+        if params.fanout == 0 || params.fanout > MAX_FANOUT {
+            return Err(format!("fanout must be between 1 and {}", MAX_FANOUT));
+        }
+        if params.depth > MAX_DEPTH {
+            return Err(format!("depth must be at most {}", MAX_DEPTH));
+        }
+        Ok(Self {
+            seed: params.seed,
+            fanout: params.fanout,
+            depth: params.depth,
+            origin: site_origin(headers).unwrap_or_default(),
+        })
+    }
+
+    fn base(&self) -> String {
+        format!("/site/{}/{}/{}", self.seed, self.fanout, self.depth)
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}{}", self.origin, self.base(), path)
+    }
+
+    /// Parses the child indices in a `/p/...` path, if they name a page in the tree.
+    fn parse_page(&self, path: &str) -> Option<Vec<u64>> {
+        let page: Vec<u64> = path
+            .split('/')
+            .map(|index| index.parse().ok().filter(|index| *index < self.fanout))
+            .collect::<Option<_>>()?;
+        (page.len() <= self.depth as usize).then_some(page)
+    }
+
+    /// Draws the page's features from its own stream, so pages do not depend on crawl order.
+    fn rng(&self, page: &[u64]) -> ChaCha8Rng {
+        let mut rng = ChaCha8Rng::seed_from_u64(self.seed);
+        rng.set_stream(
+            page.iter()
+                .fold(0, |id, index| id * (self.fanout + 1) + index + 1),
+        );
+        rng
+    }
+}
+
+fn page_path(page: &[u64]) -> String {
+    if page.is_empty() {
+        return "/".to_owned();
+    }
+    let indices: Vec<String> = page.iter().map(u64::to_string).collect();
+    format!("/p/{}", indices.join("/"))
+}
+
+#[derive(Serialize)]
+struct Link {
+    href: String,
+    text: String,
+    nofollow: bool,
+}
+
+impl Link {
+    fn new(href: String, text: impl Into<String>) -> Self {
+        Self {
+            href,
+            text: text.into(),
+            nofollow: false,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct StartParams {
+    seed: Option<u64>,
+    fanout: Option<u64>,
+    depth: Option<u32>,
+}
+
+/// Redirects to the home page of the site with the given shape, picking a seed if there is none.
+async fn start(Query(params): Query<StartParams>, headers: HeaderMap) -> Response {
+    let params = SiteParams {
+        seed: params.seed.unwrap_or_else(|| rand::thread_rng().gen()),
+        fanout: params.fanout.unwrap_or(DEFAULT_FANOUT),
+        depth: params.depth.unwrap_or(DEFAULT_DEPTH),
+    };
+    match Site::new(params, &headers) {
+        Ok(site) => (
+            StatusCode::FOUND,
+            [(header::LOCATION, format!("{}/", site.base()))],
+        )
+            .into_response(),
+        Err(message) => bad_request(&message),
+    }
+}
+
+async fn home(site: Site, headers: HeaderMap) -> Response {
+    render_page(&site, &[], &headers)
+}
+
+async fn page(
+    site: Site,
+    Path(PageParams { path }): Path<PageParams>,
+    headers: HeaderMap,
+) -> Response {
+    match site.parse_page(&path).filter(|page| !page.is_empty()) {
+        Some(page) => render_page(&site, &page, &headers),
+        None => not_found(&site, &headers),
+    }
+}
+
+#[derive(Deserialize)]
+struct PageParams {
+    path: String,
+}
+
+/// Renders a page with a canonical link to itself, links back up the tree and to each child,
+/// and a seeded selection of the traps crawlers have to handle.
+fn render_page(site: &Site, page: &[u64], headers: &HeaderMap) -> Response {
+    let mut rng = site.rng(page);
+    let base = site.base();
+    let path = page_path(page);
+
+    let mut links = vec![];
+    if !page.is_empty() {
+        links.push(Link::new(format!("{}/", base), "Home"));
+    }
+    if page.len() > 1 {
+        links.push(Link::new(
+            format!("{}{}", base, page_path(&page[..page.len() - 1])),
+            "Up",
+        ));
+    }
+    if page.len() < site.depth as usize {
+        for index in 0..site.fanout {
+            let child = page_path(&[page, &[index]].concat());
+            let href = if rng.gen_bool(0.2) {
+                let hops = rng.gen_range(1..=3);
+                format!("{}/redirect/{}{}", base, hops, child)
+            } else {
+                format!("{}{}", base, child)
+            };
+            let mut link = Link::new(href, format!("Child {}", index));
+            link.nofollow = rng.gen_bool(0.2);
+            links.push(link);
+        }
+    }
+    // The same content under a different query string, for canonicalization.
+    if rng.gen_bool(0.3) {
+        let href = format!("{}{}?ref={}", base, path, rng.gen::<u16>());
+        links.push(Link::new(href, "Share this page"));
+    }
+    if rng.gen_bool(0.2) {
+        let href = format!("{}/missing/{}", base, rng.gen::<u32>());
+        links.push(Link::new(href, "Archive"));
+    }
+    if rng.gen_bool(0.2) {
+        let href = format!("{}/private/{}", base, rng.gen::<u32>());
+        links.push(Link::new(href, "Members only"));
+    }
+
+    let title = match page {
+        [] => "Home".to_owned(),
+        page => format!(
+            "Page {}",
+            page.iter()
+                .map(u64::to_string)
+                .collect::<Vec<_>>()
+                .join(".")
+        ),
+    };
+    let text = (0..12)
+        .map(|_| *WORDS.choose(&mut rng).expect("there are words"))
+        .collect::<Vec<_>>()
+        .join(" ");
+    html(
+        render!(
+            PAGE_TEMPLATE,
+            title => title,
+            canonical => site.url(&path),
+            text => text,
+            links => links,
+        ),
+        headers,
+    )
+}
+
+#[derive(Deserialize)]
+struct IdParams {
+    id: u64,
+}
+
+/// A "soft 404": a page that says it does not exist, but with `200 OK`.
+async fn soft_not_found(
+    site: Site,
+    Path(IdParams { id }): Path<IdParams>,
+    headers: HeaderMap,
+) -> Response {
+    html(
+        render!(
+            PAGE_TEMPLATE,
+            title => "Page not found",
+            text => format!("Sorry, there is no longer anything at archive entry {}.", id),
+            links => [Link::new(format!("{}/", site.base()), "Home")],
+        ),
+        &headers,
+    )
+}
+
+/// A page that `robots.txt` disallows, which also asks not to be indexed.
+async fn private(
+    site: Site,
+    Path(IdParams { id }): Path<IdParams>,
+    headers: HeaderMap,
+) -> Response {
+    html(
+        render!(
+            PAGE_TEMPLATE,
+            title => "Members only",
+            text => format!("Crawlers should never see member {}.", id),
+            noindex => true,
+            links => [Link::new(format!("{}/", site.base()), "Home")],
+        ),
+        &headers,
+    )
+}
+
+fn not_found(site: &Site, headers: &HeaderMap) -> Response {
+    StaticContent::new(
+        mime::TEXT_HTML_UTF_8.as_ref(),
+        render!(
+            PAGE_TEMPLATE,
+            title => "Not found",
+            text => "This page is not part of the site.",
+            links => [Link::new(format!("{}/", site.base()), "Home")],
+        ),
+    )
+    .with_status(StatusCode::NOT_FOUND)
+    .respond(headers)
+}
+
+#[derive(Deserialize)]
+struct RedirectParams {
+    hops: u32,
+    target: String,
+}
+
+/// Permanently redirects through `hops` more URLs before reaching `target`.
+async fn redirect(site: Site, Path(params): Path<RedirectParams>) -> Response {
+    if params.hops == 0 || params.hops > MAX_REDIRECT_HOPS {
+        return bad_request(&format!("hops must be between 1 and {}", MAX_REDIRECT_HOPS));
+    }
+    let location = if params.hops == 1 {
+        format!("{}/{}", site.base(), params.target)
+    } else {
+        format!(
+            "{}/redirect/{}/{}",
+            site.base(),
+            params.hops - 1,
+            params.target
+        )
+    };
+    (
+        StatusCode::MOVED_PERMANENTLY,
+        [(header::LOCATION, location)],
+    )
+        .into_response()
+}
+
+async fn robots(site: Site, headers: HeaderMap) -> Response {
+    StaticContent::new(
+        mime::TEXT_PLAIN_UTF_8.as_ref(),
+        render!(ROBOTS_TEMPLATE, base => site.base(), origin => site.origin),
+    )
+    .respond(&headers)
+}
+
+/// Lists one sitemap per level of the tree.
+async fn sitemap_index(site: Site, headers: HeaderMap) -> Response {
+    let locs: Vec<String> = (0..=site.depth)
+        .map(|level| site.url(&format!("/sitemap/{}", level)))
+        .collect();
+    xml(render!(SITEMAP_INDEX_TEMPLATE, locs => locs), &headers)
+}
+
+#[derive(Deserialize)]
+struct SitemapParams {
+    level: u32,
+}
+
+/// Lists every page `level` links below the home page.
+async fn sitemap(
+    site: Site,
+    Path(SitemapParams { level }): Path<SitemapParams>,
+    headers: HeaderMap,
+) -> Response {
+    if level > site.depth {
+        return not_found(&site, &headers);
+    }
+    let locs: Vec<String> = (0..site.fanout.pow(level))
+        .map(|mut number| {
+            // Reads the page's child indices off the digits of its number in base `fanout`.
+            let mut page = vec![0; level as usize];
+            for index in page.iter_mut().rev() {
+                *index = number % site.fanout;
+                number /= site.fanout;
+            }
+            site.url(&page_path(&page))
+        })
+        .collect();
+    xml(render!(SITEMAP_TEMPLATE, locs => locs), &headers)
+}
+
+fn html(body: String, headers: &HeaderMap) -> Response {
+    StaticContent::new(mime::TEXT_HTML_UTF_8.as_ref(), body).respond(headers)
+}
+
+fn xml(body: String, headers: &HeaderMap) -> Response {
+    StaticContent::new("application/xml", body).respond(headers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::{Body, Bytes},
+        http::Request,
+    };
+    use tower::ServiceExt;
+
+    async fn get(uri: &str) -> (StatusCode, HeaderMap, Bytes) {
+        let response = routes()
+            .oneshot(
+                Request::builder()
+                    .uri(uri)
+                    .header(header::HOST, "example.com")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        (status, headers, body)
+    }
+
+    #[tokio::test]
+    async fn redirects_to_the_home_page() {
+        let (status, headers, _) = get("/site?seed=7&fanout=2").await;
+
+        assert_eq!(status, StatusCode::FOUND);
+        assert_eq!(headers[header::LOCATION], "/site/7/2/3/");
+    }
+
+    #[tokio::test]
+    async fn pages_are_reproducible() {
+        let (status, headers, first) = get("/site/7/3/2/p/1").await;
+        let (_, _, second) = get("/site/7/3/2/p/1").await;
+        let (_, _, query_variant) = get("/site/7/3/2/p/1?ref=12").await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            headers[header::CONTENT_TYPE],
+            mime::TEXT_HTML_UTF_8.as_ref()
+        );
+        assert_eq!(first, second);
+        assert_eq!(first, query_variant);
+
+        let page = String::from_utf8(first.to_vec()).unwrap();
+        assert!(
+            page.contains(r#"<link rel="canonical" href="http://example.com/site/7/3/2/p/1" />"#)
+        );
+        for child in 0..3 {
+            assert!(page.contains(&format!("/p/1/{}\"", child)), "{}", page);
+        }
+    }
+
+    #[tokio::test]
+    async fn seeds_decide_the_traps() {
+        let mut bodies = vec![];
+        for seed in 0..20 {
+            let (_, _, body) = get(&format!("/site/{}/5/2/", seed)).await;
+            bodies.push(String::from_utf8(body.to_vec()).unwrap());
+        }
+
+        assert!(bodies.iter().any(|body| body.contains(r#"rel="nofollow""#)));
+        assert!(bodies.iter().any(|body| body.contains("/redirect/")));
+        assert!(bodies.iter().any(|body| body.contains("?ref=")));
+        assert!(bodies.iter().any(|body| body.contains("/missing/")));
+        assert!(bodies.iter().any(|body| body.contains("/private/")));
+    }
+
+    #[tokio::test]
+    async fn leaves_have_no_children() {
+        let (_, _, body) = get("/site/7/3/2/p/1/2").await;
+        assert!(!String::from_utf8(body.to_vec()).unwrap().contains("Child"));
+    }
+
+    #[tokio::test]
+    async fn pages_outside_the_tree_are_not_found() {
+        for uri in ["/site/7/3/2/p/3", "/site/7/3/2/p/0/0/0", "/site/7/3/2/p/0/"] {
+            assert_eq!(get(uri).await.0, StatusCode::NOT_FOUND, "{}", uri);
+        }
+    }
+
+    #[tokio::test]
+    async fn soft_404s_succeed() {
+        let (status, _, body) = get("/site/7/3/2/missing/5").await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert!(String::from_utf8(body.to_vec())
+            .unwrap()
+            .contains("Page not found"));
+    }
+
+    #[tokio::test]
+    async fn follows_redirect_chains() {
+        let (status, headers, _) = get("/site/7/3/2/redirect/2/p/0/1").await;
+        assert_eq!(status, StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(headers[header::LOCATION], "/site/7/3/2/redirect/1/p/0/1");
+
+        let (_, headers, _) = get("/site/7/3/2/redirect/1/p/0/1").await;
+        assert_eq!(headers[header::LOCATION], "/site/7/3/2/p/0/1");
+    }
+
+    #[tokio::test]
+    async fn robots_disallows_private_pages() {
+        let (status, _, body) = get("/site/7/3/2/robots.txt").await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            body,
+            "User-agent: *\n\
+             Crawl-delay: 1\n\
+             Disallow: /site/7/3/2/private/\n\
+             \n\
+             Sitemap: http://example.com/site/7/3/2/sitemap.xml"
+        );
+    }
+
+    #[tokio::test]
+    async fn robots_leaves_out_relative_sitemaps() {
+        let response = routes()
+            .oneshot(
+                Request::builder()
+                    .uri("/site/7/3/2/robots.txt")
+                    .header(header::HOST, "example.com/evil")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+
+        assert_eq!(
+            body,
+            "User-agent: *\n\
+             Crawl-delay: 1\n\
+             Disallow: /site/7/3/2/private/\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn sitemaps_list_every_page() {
+        let (_, headers, index) = get("/site/7/2/2/sitemap.xml").await;
+        let index = String::from_utf8(index.to_vec()).unwrap();
+
+        assert_eq!(headers[header::CONTENT_TYPE], "application/xml");
+        assert!(index.starts_with("<?xml"));
+        assert_eq!(index.matches("<sitemap>").count(), 3);
+        assert!(index.contains("<loc>http://example.com/site/7/2/2/sitemap/2</loc>"));
+
+        let (_, _, sitemap) = get("/site/7/2/2/sitemap/2").await;
+        let sitemap = String::from_utf8(sitemap.to_vec()).unwrap();
+        let locs: Vec<&str> = sitemap
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("<url><loc>"))
+            .collect();
+        assert_eq!(
+            locs,
+            ["0/0", "0/1", "1/0", "1/1"]
+                .map(|page| format!("http://example.com/site/7/2/2/p/{}</loc></url>", page))
+        );
+
+        assert_eq!(get("/site/7/2/2/sitemap/3").await.0, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn ignores_origins_that_are_not_plain() {
+        for (host, proto) in [
+            (r#"example.com"><script>alert(1)</script>"#, "http"),
+            ("example.com/evil", "http"),
+            ("user@example.com", "http"),
+            ("example.com", "javascript"),
+            ("example.com", r#"http"><x"#),
+        ] {
+            let response = routes()
+                .oneshot(
+                    Request::builder()
+                        .uri("/site/7/3/2/p/1")
+                        .header(header::HOST, host)
+                        .header("x-forwarded-proto", proto)
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+            let page = String::from_utf8(body.to_vec()).unwrap();
+
+            assert!(
+                page.contains(r#"<link rel="canonical" href="/site/7/3/2/p/1" />"#),
+                "{} {}",
+                host,
+                proto
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_shapes() {
+        for uri in [
+            "/site/7/0/2/",
+            "/site/7/11/2/",
+            "/site/7/3/5/",
+            "/site?depth=9",
+        ] {
+            assert_eq!(get(uri).await.0, StatusCode::BAD_REQUEST, "{}", uri);
+        }
+    }
+}
@@ -0,0 +1,211 @@
+use crate::{compression::Uncompressed, config, echo::bad_request};
+use axum::{
+    extract::Query,
+    http::{HeaderMap, StatusCode},
+    response::{
+        sse::{Event, Sse},
+        IntoResponse, Response,
+    },
+    routing::get,
+    Router,
+};
+use futures_util::stream;
+use serde::Deserialize;
+use serde_json::json;
+use std::time::Duration;
+
+const MAX_EVENTS: u64 = 1000;
+
+pub fn routes() -> Router {
+    Router::new().route("/sse", get(sse))
+}
+
+#[derive(Deserialize)]
+struct SseParams {
+    count: Option<u64>,
+    interval: Option<f64>,
+    event: Option<String>,
+    retry: Option<u64>,
+    drop_after: Option<u64>,
+}
+
+/// Ends the response without the final chunk, so the client sees the connection drop.
+#[derive(Debug)]
+struct Dropped;
+
+impl std::fmt::Display for Dropped {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("dropped the event stream on request")
+    }
+}
+
+impl std::error::Error for Dropped {}
+
+/// Sends events numbered 1 to `count`, `interval` seconds apart, each with its number as the
+/// `id` and a pretty-printed JSON object spread over several `data:` lines.
+///
+/// A reconnecting client's `Last-Event-ID` resumes the stream after that event. Once every
+/// event has been seen, the reply is `204 No Content`, which tells an `EventSource` to stop
+/// reconnecting. An ID this endpoint could not have sent starts again from the beginning.
+async fn sse(Query(params): Query<SseParams>, headers: HeaderMap) -> Response {
+    let count = params.count.unwrap_or(10);
+    if count > MAX_EVENTS {
+        return bad_request(&format!("count must be at most {}", MAX_EVENTS));
+    }
+    let interval = match config::parse_duration("interval", params.interval.unwrap_or(1.0)) {
+        Ok(interval) => interval,
+        Err(message) => return bad_request(&message),
+    };
+    if params
+        .event
+        .as_deref()
+        .is_some_and(|event| event.contains(['\r', '\n']))
+    {
+        return bad_request("event must be a single line");
+    }
+    if params.drop_after == Some(0) {
+        return bad_request("drop_after must be at least 1");
+    }
+
+    let last_id = headers
+        .get("last-event-id")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .filter(|last_id| *last_id <= count)
+        .unwrap_or(0);
+    if last_id == count {
+        return StatusCode::NO_CONTENT.into_response();
+    }
+
+    let first = last_id + 1;
+    let (name, retry, drop_after) = (params.event, params.retry, params.drop_after);
+    let events = stream::unfold(Some(first), move |next| {
+        let name = name.clone();
+        async move {
+            let id = next.filter(|id| *id <= count)?;
+            if id > first {
+                tokio::time::sleep(interval).await;
+            }
+            if drop_after.is_some_and(|drop_after| id - first == drop_after) {
+                return Some((Err(Dropped), None));
+            }
+
+            let data = json!({ "id": id, "count": count });
+            let mut event = Event::default()
+                .id(id.to_string())
+                .data(serde_json::to_string_pretty(&data).expect("events always serialize"));
+            if let Some(name) = name {
+                event = event.event(name);
+            }
+            // The reconnection delay only needs telling once per connection.
+            if let Some(retry) = retry.filter(|_| id == first) {
+                event = event.retry(Duration::from_millis(retry));
+            }
+            Some((Ok(event), Some(id + 1)))
+        }
+    });
+
+    let mut response = Sse::new(events).into_response();
+    response.extensions_mut().insert(Uncompressed);
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::{Body, HttpBody},
+        http::{header, HeaderValue, Request},
+    };
+    use tower::ServiceExt;
+
+    async fn get(uri: &str, last_event_id: Option<&str>) -> Response {
+        let mut request = Request::builder().uri(uri);
+        if let Some(last_event_id) = last_event_id {
+            request = request.header("last-event-id", last_event_id);
+        }
+        routes()
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    async fn text(response: Response) -> String {
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn sends_numbered_events() {
+        let response = get("/sse?count=2&interval=0&event=tick&retry=500", None).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE),
+            Some(&HeaderValue::from_static(mime::TEXT_EVENT_STREAM.as_ref()))
+        );
+        assert_eq!(
+            text(response).await,
+            "id:1\ndata:{\ndata:   \"count\": 2,\ndata:   \"id\": 1\ndata:}\n\
+             event:tick\nretry:500\n\n\
+             id:2\ndata:{\ndata:   \"count\": 2,\ndata:   \"id\": 2\ndata:}\n\
+             event:tick\n\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn resumes_after_last_event_id() {
+        let body = text(get("/sse?count=3&interval=0", Some("2")).await).await;
+        assert!(body.starts_with("id:3\n"));
+        assert!(!body.contains("\nid:"));
+
+        let body = text(get("/sse?count=3&interval=0", Some("not-ours")).await).await;
+        assert!(body.starts_with("id:1\n"));
+    }
+
+    #[tokio::test]
+    async fn tells_clients_to_stop_after_the_last_event() {
+        let response = get("/sse?count=3", Some("3")).await;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    }
+
+    #[tokio::test]
+    async fn drops_the_connection_after_k_events() {
+        let response = get("/sse?count=5&interval=0&drop_after=2", None).await;
+
+        let mut body = response.into_body();
+        let mut events = 0;
+        let error = loop {
+            match body.data().await {
+                Some(Ok(_)) => events += 1,
+                Some(Err(error)) => break error,
+                None => panic!("the stream ended cleanly"),
+            }
+        };
+        assert_eq!(events, 2);
+        assert!(error.to_string().contains("dropped"));
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_parameters() {
+        for uri in [
+            "/sse?count=1001",
+            "/sse?event=a%0Ab",
+            "/sse?drop_after=0",
+            "/sse?interval=-1",
+        ] {
+            assert_eq!(
+                get(uri, None).await.status(),
+                StatusCode::BAD_REQUEST,
+                "{}",
+                uri
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn names_the_invalid_interval() {
+        let body = text(get("/sse?interval=-1", None).await).await;
+        assert!(body.starts_with("interval must be between 0 and "), "{}", body);
+    }
+}
@@ -201,7 +201,7 @@ mod tests {
             .await
             .unwrap();
 
-        let choices = vec![
+        let choices = [
             StatusCode::OK,
             StatusCode::CREATED,
             StatusCode::ACCEPTED,
@@ -225,7 +225,7 @@ mod tests {
             .await
             .unwrap();
 
-        let choices = vec![
+        let choices = [
             StatusCode::OK,
             StatusCode::CREATED,
             StatusCode::ACCEPTED,
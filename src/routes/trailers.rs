@@ -0,0 +1,385 @@
+use crate::{
+    compression::Uncompressed,
+    echo::{bad_request, headers_to_json},
+};
+use axum::{
+    body::{boxed, Body, Bytes, HttpBody},
+    extract::Query,
+    http::{header, HeaderMap, HeaderName, HeaderValue, Request, StatusCode, Version},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::VecDeque,
+    convert::Infallible,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Instant,
+};
+
+const CHUNKS: [&str; 3] = [
+    "This body is sent in chunks,\n",
+    "and the trailers listed in the Trailer header\n",
+    "follow the last one.\n",
+];
+
+/// Fields that RFC 9110 section 6.5.1 rules out of trailers because recipients need them
+/// before the content, or because they describe the connection rather than the message.
+const FORBIDDEN_TRAILERS: [HeaderName; 21] = [
+    header::AGE,
+    header::AUTHORIZATION,
+    header::CACHE_CONTROL,
+    header::CONNECTION,
+    header::CONTENT_ENCODING,
+    header::CONTENT_LENGTH,
+    header::CONTENT_RANGE,
+    header::CONTENT_TYPE,
+    header::DATE,
+    header::EXPIRES,
+    header::HOST,
+    header::LOCATION,
+    header::PROXY_AUTHENTICATE,
+    header::PROXY_AUTHORIZATION,
+    header::RETRY_AFTER,
+    header::SET_COOKIE,
+    header::TE,
+    header::TRAILER,
+    header::TRANSFER_ENCODING,
+    header::UPGRADE,
+    header::VARY,
+];
+
+pub fn routes() -> Router {
+    Router::new()
+        .route("/request-trailers", post(request_trailers))
+        .route("/response-trailers", get(response_trailers))
+}
+
+/// A body sent a chunk at a time, followed by trailers.
+struct TrailingBody {
+    chunks: VecDeque<Bytes>,
+    trailers: Option<HeaderMap>,
+    started: Instant,
+}
+
+impl HttpBody for TrailingBody {
+    type Data = Bytes;
+    type Error = Infallible;
+
+    fn poll_data(
+        mut self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        Poll::Ready(self.chunks.pop_front().map(Ok))
+    }
+
+    fn poll_trailers(
+        mut self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        let mut trailers = self.trailers.take();
+        if let Some(trailers) = &mut trailers {
+            // Only known once everything else has been sent.
+            let elapsed = self.started.elapsed().as_secs_f64() * 1000.0;
+            trailers.insert(
+                HeaderName::from_static("server-timing"),
+                HeaderValue::from_str(&format!("total;dur={:.3}", elapsed))
+                    .expect("timings are valid headers"),
+            );
+        }
+        Poll::Ready(Ok(trailers))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.chunks.is_empty() && self.trailers.is_none()
+    }
+}
+
+/// Streams a short body followed by `Content-Digest` and `Server-Timing` trailers, plus one for
+/// each query parameter, all of which are announced in the `Trailer` header.
+///
+/// hyper 0.14 cannot write the trailer section of a chunked HTTP/1.1 body, so HTTP/1.x requests
+/// are refused with `505 HTTP Version Not Supported` rather than sent a body without them.
+async fn response_trailers(
+    version: Version,
+    Query(params): Query<Vec<(String, String)>>,
+) -> Response {
+    if version != Version::HTTP_2 {
+        return (
+            StatusCode::HTTP_VERSION_NOT_SUPPORTED,
+            "Trailers can only be sent over HTTP/2",
+        )
+            .into_response();
+    }
+    let mut trailers = HeaderMap::new();
+    for (name, value) in params {
+        let Ok(name) = HeaderName::from_bytes(name.as_bytes()) else {
+            return bad_request(&format!("Invalid trailer name {:?}", name));
+        };
+        if FORBIDDEN_TRAILERS.contains(&name) {
+            return bad_request(&format!("{} may not be sent as a trailer", name));
+        }
+        if name == "content-digest" || name == "server-timing" {
+            return bad_request(&format!("{} is always set by the server", name));
+        }
+        let Ok(value) = HeaderValue::from_str(&value) else {
+            return bad_request(&format!("Invalid value for trailer {}", name));
+        };
+        trailers.append(name, value);
+    }
+
+    let digest = Sha256::digest(CHUNKS.concat());
+    trailers.insert(
+        HeaderName::from_static("content-digest"),
+        HeaderValue::from_str(&format!("sha-256=:{}:", STANDARD.encode(digest)))
+            .expect("digests are valid headers"),
+    );
+    let mut announced: Vec<&str> = trailers.keys().map(HeaderName::as_str).collect();
+    announced.push("server-timing");
+    let announced = announced.join(", ");
+
+    let body = TrailingBody {
+        chunks: CHUNKS
+            .into_iter()
+            .map(|chunk| Bytes::from_static(chunk.as_bytes()))
+            .collect(),
+        trailers: Some(trailers),
+        started: Instant::now(),
+    };
+    let mut response = (
+        [
+            (header::CONTENT_TYPE, mime::TEXT_PLAIN_UTF_8.to_string()),
+            (header::TRAILER, announced),
+        ],
+        boxed(body),
+    )
+        .into_response();
+    // Compressing would replace the body, and the trailers with it.
+    response.extensions_mut().insert(Uncompressed);
+    response
+}
+
+/// Reads the request body and reports the trailers that followed it.
+///
+/// hyper 0.14 discards the trailers of chunked HTTP/1.1 requests, so for HTTP/1.x `trailers` is
+/// `null`, with a note saying why, rather than an empty object claiming none were sent.
+async fn request_trailers(request: Request<Body>) -> Response {
+    let observable = request.version() == Version::HTTP_2;
+    let version = format!("{:?}", request.version());
+    let mut body = request.into_body();
+    let mut bytes = 0;
+    while let Some(chunk) = body.data().await {
+        match chunk {
+            Ok(chunk) => bytes += chunk.len(),
+            Err(error) => return bad_request(&format!("Failed to read body: {}", error)),
+        }
+    }
+    let trailers = match body.trailers().await {
+        Ok(trailers) => trailers.unwrap_or_default(),
+        Err(error) => return bad_request(&format!("Failed to read trailers: {}", error)),
+    };
+
+    if !observable {
+        return Json(json!({
+            "bytes": bytes,
+            "http_version": version,
+            "trailers": null,
+            "note": "Request trailers can only be observed over HTTP/2",
+        }))
+        .into_response();
+    }
+    Json(json!({
+        "bytes": bytes,
+        "http_version": version,
+        "trailers": headers_to_json(&trailers),
+    }))
+    .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::Client;
+    use serde_json::Value;
+    use std::net::SocketAddr;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+    use tower::ServiceExt;
+
+    async fn serve() -> SocketAddr {
+        let server = axum::Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0)))
+            .serve(routes().into_make_service());
+        let addr = server.local_addr();
+        tokio::spawn(server);
+        addr
+    }
+
+    #[tokio::test]
+    async fn sends_trailers_over_http2() {
+        let addr = serve().await;
+        let client = Client::builder().http2_only(true).build_http::<Body>();
+        let response = client
+            .get(
+                format!(
+                    "http://{}/response-trailers?grpc-status=0&grpc-message=ok",
+                    addr
+                )
+                .parse()
+                .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.version(), Version::HTTP_2);
+        let mut body = response.into_body();
+        let mut received = vec![];
+        while let Some(chunk) = body.data().await {
+            received.extend_from_slice(&chunk.unwrap());
+        }
+        assert_eq!(received, CHUNKS.concat().as_bytes());
+
+        let trailers = body.trailers().await.unwrap().unwrap();
+        assert_eq!(trailers["grpc-status"], "0");
+        assert_eq!(trailers["grpc-message"], "ok");
+        assert_eq!(
+            trailers["content-digest"],
+            format!("sha-256=:{}:", STANDARD.encode(Sha256::digest(&received)))
+        );
+        assert!(trailers["server-timing"]
+            .to_str()
+            .unwrap()
+            .starts_with("total;dur="));
+    }
+
+    #[tokio::test]
+    async fn announces_trailers() {
+        let response = routes()
+            .oneshot(
+                Request::builder()
+                    .uri("/response-trailers?x-a=1&x-b=2")
+                    .version(Version::HTTP_2)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::TRAILER],
+            "x-a, x-b, content-digest, server-timing"
+        );
+        assert!(response.extensions().get::<Uncompressed>().is_some());
+    }
+
+    #[tokio::test]
+    async fn refuses_response_trailers_over_http1() {
+        let addr = serve().await;
+        let response = Client::new()
+            .get(
+                format!("http://{}/response-trailers?grpc-status=0", addr)
+                    .parse()
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::HTTP_VERSION_NOT_SUPPORTED);
+        assert!(!response.headers().contains_key(header::TRAILER));
+    }
+
+    #[tokio::test]
+    async fn reports_request_trailers_over_http2() {
+        let addr = serve().await;
+        let client = Client::builder().http2_only(true).build_http::<Body>();
+        let (mut sender, body) = Body::channel();
+        let request = Request::post(format!("http://{}/request-trailers", addr))
+            .body(body)
+            .unwrap();
+        let response = tokio::spawn(client.request(request));
+
+        sender
+            .send_data(Bytes::from_static(b"hello"))
+            .await
+            .unwrap();
+        let mut trailers = HeaderMap::new();
+        trailers.insert("x-checksum", HeaderValue::from_static("abc"));
+        sender.send_trailers(trailers).await.unwrap();
+        drop(sender);
+
+        let response = response.await.unwrap().unwrap();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body,
+            json!({
+                "bytes": 5,
+                "http_version": "HTTP/2.0",
+                "trailers": { "x-checksum": "abc" },
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_request_trailers_as_unobservable_over_http1() {
+        let addr = serve().await;
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(
+                b"POST /request-trailers HTTP/1.1\r\n\
+                  Host: localhost\r\n\
+                  Transfer-Encoding: chunked\r\n\
+                  Trailer: x-checksum\r\n\
+                  Connection: close\r\n\
+                  \r\n\
+                  5\r\nhello\r\n\
+                  0\r\n\
+                  x-checksum: abc\r\n\
+                  \r\n",
+            )
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+        let body: Value = serde_json::from_str(response.split("\r\n\r\n").nth(1).unwrap()).unwrap();
+        assert_eq!(
+            body,
+            json!({
+                "bytes": 5,
+                "http_version": "HTTP/1.1",
+                "trailers": null,
+                "note": "Request trailers can only be observed over HTTP/2",
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_trailers_that_must_be_headers() {
+        for query in [
+            "content-length=5",
+            "host=example.com",
+            "server-timing=x",
+            "bad%20name=1",
+        ] {
+            let response = routes()
+                .oneshot(
+                    Request::builder()
+                        .uri(format!("/response-trailers?{}", query))
+                        .version(Version::HTTP_2)
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", query);
+        }
+    }
+}
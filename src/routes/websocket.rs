@@ -0,0 +1,708 @@
+use crate::{config, echo::bad_request};
+use axum::{
+    body::Body,
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Path, Query, State,
+    },
+    http::{header, HeaderMap, HeaderValue, Request, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use futures_util::{SinkExt, StreamExt};
+use hyper::upgrade::Upgraded;
+use serde::Deserialize;
+use serde_json::json;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{
+    sync::broadcast::{self, error::RecvError},
+    time::{interval_at, Instant, Interval},
+};
+use tokio_tungstenite::{
+    tungstenite::{
+        self,
+        handshake::derive_accept_key,
+        protocol::{
+            frame::{
+                coding::{CloseCode, Data, OpCode},
+                Frame,
+            },
+            CloseFrame, Role, WebSocketConfig,
+        },
+    },
+    WebSocketStream,
+};
+
+/// Close frames carry at most 125 bytes, two of which are the code.
+const MAX_CLOSE_REASON: usize = 123;
+/// The largest message `/ws/echo` accepts.
+const MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+/// How many messages a slow room member may fall behind before it starts missing them.
+const ROOM_BACKLOG: usize = 64;
+
+pub fn routes() -> Router {
+    Router::new()
+        .route("/ws/echo", get(echo))
+        .route("/ws/room/:name", get(join_room))
+        .route("/ws/rooms", get(list_rooms))
+        .with_state(Rooms::default())
+}
+
+#[derive(Deserialize)]
+struct EchoParams {
+    protocols: Option<String>,
+    ping_interval: Option<f64>,
+    close_after: Option<u64>,
+    close_code: Option<u16>,
+    close_reason: Option<String>,
+    fragment: Option<usize>,
+}
+
+struct EchoOptions {
+    ping_interval: Option<Duration>,
+    close_after: Option<u64>,
+    close_code: u16,
+    close_reason: String,
+    fragment: Option<usize>,
+}
+
+/// Echoes text and binary messages back to the client.
+///
+/// `protocols` lists the subprotocols the server will agree to in order of preference, and the
+/// first of them that the client also offers is selected. `ping_interval` has the server send
+/// pings of its own, and `close_after` closes the connection with `close_code` and
+/// `close_reason` once that many messages have been echoed. `fragment` splits each echoed
+/// message into frames of at most that many bytes.
+///
+/// axum's `WebSocket` cannot send a message as several frames, so the upgraded connection is
+/// handed to tungstenite directly instead. Extensions such as `permessage-deflate` are never
+/// negotiated.
+async fn echo(Query(params): Query<EchoParams>, mut request: Request<Body>) -> Response {
+    let ping_interval = match params
+        .ping_interval
+        .map(|seconds| config::parse_duration("ping_interval", seconds))
+        .transpose()
+    {
+        Ok(Some(interval)) if interval.is_zero() => {
+            return bad_request("ping_interval must be more than 0 seconds")
+        }
+        Ok(interval) => interval,
+        Err(message) => return bad_request(&message),
+    };
+    if params.close_after == Some(0) {
+        return bad_request("close_after must be at least 1");
+    }
+    let close_code = params.close_code.unwrap_or(1000);
+    if !is_sendable_close_code(close_code) {
+        return bad_request(&format!(
+            "close_code {} may not be sent in a close frame",
+            close_code
+        ));
+    }
+    let close_reason = params.close_reason.unwrap_or_default();
+    if close_reason.len() > MAX_CLOSE_REASON {
+        return bad_request(&format!(
+            "close_reason must be at most {} bytes",
+            MAX_CLOSE_REASON
+        ));
+    }
+    if params.fragment == Some(0) {
+        return bad_request("fragment must be at least 1");
+    }
+
+    let headers = request.headers();
+    let accept = match accept_key(headers) {
+        Ok(accept) => accept,
+        Err(message) => return bad_request(message),
+    };
+    let offered: Vec<&str> = headers
+        .get_all(header::SEC_WEBSOCKET_PROTOCOL)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .collect();
+    let protocol = params
+        .protocols
+        .iter()
+        .flat_map(|protocols| protocols.split(','))
+        .map(str::trim)
+        .find(|protocol| !protocol.is_empty() && offered.contains(protocol))
+        .and_then(|protocol| HeaderValue::from_str(protocol).ok());
+
+    let options = EchoOptions {
+        ping_interval,
+        close_after: params.close_after,
+        close_code,
+        close_reason,
+        fragment: params.fragment,
+    };
+    let upgrade = hyper::upgrade::on(&mut request);
+    tokio::spawn(async move {
+        let upgraded = match upgrade.await {
+            Ok(upgraded) => upgraded,
+            Err(error) => return tracing::debug!("WebSocket upgrade failed: {}", error),
+        };
+        let config = WebSocketConfig {
+            max_message_size: Some(MAX_MESSAGE_SIZE),
+            max_frame_size: Some(MAX_MESSAGE_SIZE),
+            ..Default::default()
+        };
+        let socket = WebSocketStream::from_raw_socket(upgraded, Role::Server, Some(config)).await;
+        if let Err(error) = echo_messages(socket, options).await {
+            tracing::debug!("WebSocket echo ended: {}", error);
+        }
+    });
+
+    let mut response = StatusCode::SWITCHING_PROTOCOLS.into_response();
+    let headers = response.headers_mut();
+    headers.insert(header::CONNECTION, HeaderValue::from_static("upgrade"));
+    headers.insert(header::UPGRADE, HeaderValue::from_static("websocket"));
+    headers.insert(header::SEC_WEBSOCKET_ACCEPT, accept);
+    if let Some(protocol) = protocol {
+        headers.insert(header::SEC_WEBSOCKET_PROTOCOL, protocol);
+    }
+    response
+}
+
+/// Checks the client's opening handshake (RFC 6455 section 4.2.1) and returns the
+/// `Sec-WebSocket-Accept` value that completes it.
+fn accept_key(headers: &HeaderMap) -> Result<HeaderValue, &'static str> {
+    let has_token = |name, token: &str| {
+        headers
+            .get_all(name)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .any(|value| value.trim().eq_ignore_ascii_case(token))
+    };
+    if !has_token(header::CONNECTION, "upgrade") {
+        return Err("Connection header did not include upgrade");
+    }
+    if !has_token(header::UPGRADE, "websocket") {
+        return Err("Upgrade header did not include websocket");
+    }
+    if headers.get(header::SEC_WEBSOCKET_VERSION) != Some(&HeaderValue::from_static("13")) {
+        return Err("Sec-WebSocket-Version must be 13");
+    }
+    let key = headers
+        .get(header::SEC_WEBSOCKET_KEY)
+        .ok_or("Sec-WebSocket-Key header is missing")?;
+    Ok(HeaderValue::from_str(&derive_accept_key(key.as_bytes())).expect("keys are base64"))
+}
+
+/// Codes from RFC 6455 section 7.4 that an endpoint may put on the wire, plus the ranges
+/// reserved for libraries and applications.
+fn is_sendable_close_code(code: u16) -> bool {
+    matches!(code, 1000..=1003 | 1007..=1014 | 3000..=4999)
+}
+
+type EchoSocket = WebSocketStream<Upgraded>;
+
+async fn echo_messages(mut socket: EchoSocket, options: EchoOptions) -> tungstenite::Result<()> {
+    let mut pings = options
+        .ping_interval
+        .map(|period| interval_at(Instant::now() + period, period));
+    let mut sent_pings: u64 = 0;
+    let mut echoed = 0;
+
+    loop {
+        let message = tokio::select! {
+            message = socket.next() => message,
+            _ = tick(&mut pings) => {
+                sent_pings += 1;
+                socket.send(tungstenite::Message::Ping(sent_pings.to_be_bytes().to_vec())).await?;
+                continue;
+            }
+        };
+        // tungstenite answers pings and close frames itself, and ends the stream once the
+        // closing handshake is done.
+        let message = match message.transpose()? {
+            Some(message @ (tungstenite::Message::Text(_) | tungstenite::Message::Binary(_))) => {
+                message
+            }
+            Some(_) => continue,
+            None => return Ok(()),
+        };
+        send_fragmented(&mut socket, message, options.fragment).await?;
+
+        echoed += 1;
+        if options.close_after == Some(echoed) {
+            socket
+                .close(Some(CloseFrame {
+                    code: CloseCode::from(options.close_code),
+                    reason: options.close_reason.into(),
+                }))
+                .await?;
+            // Wait for the client to acknowledge the close before dropping the connection.
+            while socket.next().await.transpose()?.is_some() {}
+            return Ok(());
+        }
+    }
+}
+
+/// Sends a text or binary message, split into frames of at most `fragment` bytes.
+async fn send_fragmented(
+    socket: &mut EchoSocket,
+    message: tungstenite::Message,
+    fragment: Option<usize>,
+) -> tungstenite::Result<()> {
+    let Some(fragment) = fragment.filter(|fragment| *fragment < message.len()) else {
+        return socket.send(message).await;
+    };
+    let opcode = if message.is_text() {
+        Data::Text
+    } else {
+        Data::Binary
+    };
+    let payload = message.into_data();
+    let chunks: Vec<&[u8]> = payload.chunks(fragment).collect();
+    let last = chunks.len() - 1;
+    for (i, chunk) in chunks.into_iter().enumerate() {
+        let opcode = if i == 0 { opcode } else { Data::Continue };
+        let frame = Frame::message(chunk.to_vec(), OpCode::Data(opcode), i == last);
+        socket.feed(tungstenite::Message::Frame(frame)).await?;
+    }
+    socket.flush().await
+}
+
+async fn tick(interval: &mut Option<Interval>) {
+    match interval {
+        Some(interval) => {
+            interval.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// The rooms that currently have members, shared by every connection to this server.
+#[derive(Clone, Default)]
+struct Rooms(Arc<Mutex<HashMap<String, Room>>>);
+
+struct Room {
+    members: usize,
+    next_member: u64,
+    sender: broadcast::Sender<RoomMessage>,
+}
+
+#[derive(Clone)]
+struct RoomMessage {
+    from: u64,
+    message: Message,
+}
+
+impl Rooms {
+    /// Adds a member to the room, creating it if needed, and announces them to the others.
+    fn join(&self, name: &str) -> (u64, usize, broadcast::Receiver<RoomMessage>) {
+        let mut rooms = self.0.lock().expect("room lock poisoned");
+        let room = rooms.entry(name.to_owned()).or_insert_with(|| Room {
+            members: 0,
+            next_member: 1,
+            sender: broadcast::channel(ROOM_BACKLOG).0,
+        });
+        let member = room.next_member;
+        room.next_member += 1;
+        room.members += 1;
+        let receiver = room.sender.subscribe();
+        let _ = room.sender.send(presence("join", member, room.members));
+        (member, room.members, receiver)
+    }
+
+    /// Removes a member, dropping the room once it is empty.
+    fn leave(&self, name: &str, member: u64) {
+        let mut rooms = self.0.lock().expect("room lock poisoned");
+        let Some(room) = rooms.get_mut(name) else {
+            return;
+        };
+        room.members -= 1;
+        if room.members == 0 {
+            rooms.remove(name);
+        } else {
+            let _ = room.sender.send(presence("leave", member, room.members));
+        }
+    }
+
+    fn send(&self, name: &str, from: u64, message: Message) {
+        if let Some(room) = self.0.lock().expect("room lock poisoned").get(name) {
+            let _ = room.sender.send(RoomMessage { from, message });
+        }
+    }
+}
+
+/// A JSON text message about `member`, sent from them so they do not receive it themselves.
+fn presence(event: &str, member: u64, members: usize) -> RoomMessage {
+    let text = json!({ "event": event, "member": member, "members": members }).to_string();
+    RoomMessage {
+        from: member,
+        message: Message::Text(text),
+    }
+}
+
+/// Joins the room `name`, relaying every text and binary message to all other members.
+///
+/// On joining, the client is sent `{"event": "welcome", "member": id, "members": count}`, and
+/// the rest of the room gets a `join` event of the same shape. A `leave` event follows when the
+/// connection ends.
+async fn join_room(
+    Path(name): Path<String>,
+    State(rooms): State<Rooms>,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    upgrade.on_upgrade(move |socket| relay_room(socket, rooms, name))
+}
+
+async fn relay_room(mut socket: WebSocket, rooms: Rooms, name: String) {
+    let (member, members, mut receiver) = rooms.join(&name);
+    let welcome = json!({ "event": "welcome", "member": member, "members": members });
+    if socket
+        .send(Message::Text(welcome.to_string()))
+        .await
+        .is_ok()
+    {
+        loop {
+            tokio::select! {
+                message = socket.recv() => match message {
+                    Some(Ok(message @ (Message::Text(_) | Message::Binary(_)))) => {
+                        rooms.send(&name, member, message)
+                    }
+                    Some(Ok(Message::Ping(_) | Message::Pong(_))) => {}
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                },
+                relayed = receiver.recv() => match relayed {
+                    Ok(RoomMessage { from, .. }) if from == member => {}
+                    Ok(RoomMessage { message, .. }) => {
+                        if socket.send(message).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(RecvError::Lagged(missed)) => {
+                        tracing::debug!("member {} of {} missed {} messages", member, name, missed)
+                    }
+                    Err(RecvError::Closed) => break,
+                },
+            }
+        }
+    }
+    rooms.leave(&name, member);
+}
+
+/// Lists every room with members, and how many each has.
+async fn list_rooms(State(rooms): State<Rooms>) -> Json<BTreeMap<String, usize>> {
+    let rooms = rooms.0.lock().expect("room lock poisoned");
+    Json(
+        rooms
+            .iter()
+            .map(|(name, room)| (name.clone(), room.members))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{io::Cursor, net::SocketAddr};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+    use tokio_tungstenite::{
+        connect_async,
+        tungstenite::{
+            client::IntoClientRequest, protocol::frame::FrameHeader, Error,
+            Message as ClientMessage,
+        },
+    };
+
+    async fn serve() -> SocketAddr {
+        let server = axum::Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0)))
+            .serve(routes().into_make_service());
+        let addr = server.local_addr();
+        tokio::spawn(server);
+        addr
+    }
+
+    #[tokio::test]
+    async fn echoes_messages_with_the_selected_protocol() {
+        let addr = serve().await;
+        let mut request = format!("ws://{}/ws/echo?protocols=chat,superchat", addr)
+            .into_client_request()
+            .unwrap();
+        request.headers_mut().insert(
+            header::SEC_WEBSOCKET_PROTOCOL,
+            "superchat, chat".parse().unwrap(),
+        );
+        let (mut socket, response) = connect_async(request).await.unwrap();
+
+        assert_eq!(response.headers()[header::SEC_WEBSOCKET_PROTOCOL], "chat");
+        socket.send(ClientMessage::text("hello")).await.unwrap();
+        assert_eq!(
+            socket.next().await.unwrap().unwrap(),
+            ClientMessage::text("hello")
+        );
+        socket
+            .send(ClientMessage::binary(vec![0, 255]))
+            .await
+            .unwrap();
+        assert_eq!(
+            socket.next().await.unwrap().unwrap(),
+            ClientMessage::binary(vec![0, 255])
+        );
+    }
+
+    #[tokio::test]
+    async fn sends_pings() {
+        let addr = serve().await;
+        let (mut socket, _) = connect_async(format!("ws://{}/ws/echo?ping_interval=0.01", addr))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            socket.next().await.unwrap().unwrap(),
+            ClientMessage::Ping(1u64.to_be_bytes().to_vec())
+        );
+    }
+
+    #[tokio::test]
+    async fn closes_after_n_messages() {
+        let addr = serve().await;
+        let (mut socket, _) = connect_async(format!(
+            "ws://{}/ws/echo?close_after=2&close_code=4000&close_reason=bye",
+            addr
+        ))
+        .await
+        .unwrap();
+
+        for text in ["one", "two"] {
+            socket.send(ClientMessage::text(text)).await.unwrap();
+            assert_eq!(
+                socket.next().await.unwrap().unwrap(),
+                ClientMessage::text(text)
+            );
+        }
+        match socket.next().await.unwrap().unwrap() {
+            ClientMessage::Close(Some(frame)) => {
+                assert_eq!(frame.code, CloseCode::from(4000));
+                assert_eq!(frame.reason, "bye");
+            }
+            message => panic!("expected a close frame, got {:?}", message),
+        }
+    }
+
+    #[tokio::test]
+    async fn refuses_invalid_options() {
+        let addr = serve().await;
+        for (query, status) in [
+            ("fragment=0", StatusCode::BAD_REQUEST),
+            ("close_code=1005", StatusCode::BAD_REQUEST),
+            ("close_after=0", StatusCode::BAD_REQUEST),
+            ("ping_interval=0", StatusCode::BAD_REQUEST),
+        ] {
+            match connect_async(format!("ws://{}/ws/echo?{}", addr, query)).await {
+                Err(Error::Http(response)) => assert_eq!(response.status(), status, "{}", query),
+                result => panic!(
+                    "{}: expected an HTTP error, got {:?}",
+                    query,
+                    result.is_ok()
+                ),
+            }
+        }
+    }
+
+    /// Opens `/ws/echo` over a plain TCP stream, so that frames can be inspected one by one.
+    async fn raw_handshake(addr: SocketAddr, query: &str) -> TcpStream {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let request = format!(
+            "GET /ws/echo?{} HTTP/1.1\r\nHost: {}\r\nConnection: Upgrade\r\n\
+             Upgrade: websocket\r\nSec-WebSocket-Version: 13\r\n\
+             Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\r\n",
+            query, addr
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+
+        let mut head = vec![];
+        while !head.ends_with(b"\r\n\r\n") {
+            head.push(stream.read_u8().await.unwrap());
+        }
+        assert!(head.starts_with(b"HTTP/1.1 101"));
+        stream
+    }
+
+    async fn send_frame(stream: &mut TcpStream, mut frame: Frame) {
+        frame.header_mut().mask = Some([1, 2, 3, 4]);
+        let mut bytes = vec![];
+        frame.format(&mut bytes).unwrap();
+        stream.write_all(&bytes).await.unwrap();
+    }
+
+    async fn read_frame(stream: &mut TcpStream) -> Frame {
+        let mut head = vec![0; 2];
+        stream.read_exact(&mut head).await.unwrap();
+        let extra = match head[1] & 0x7f {
+            126 => 2,
+            127 => 8,
+            _ => 0,
+        };
+        head.resize(2 + extra, 0);
+        stream.read_exact(&mut head[2..]).await.unwrap();
+        let (header, length) = FrameHeader::parse(&mut Cursor::new(&head))
+            .unwrap()
+            .unwrap();
+        let mut payload = vec![0; length as usize];
+        stream.read_exact(&mut payload).await.unwrap();
+        Frame::from_payload(header, payload)
+    }
+
+    #[tokio::test]
+    async fn fragments_echoed_messages() {
+        let addr = serve().await;
+        let mut stream = raw_handshake(addr, "fragment=4").await;
+
+        send_frame(
+            &mut stream,
+            Frame::message(b"hello world".to_vec(), OpCode::Data(Data::Text), true),
+        )
+        .await;
+        let mut frames = vec![];
+        for _ in 0..3 {
+            let frame = read_frame(&mut stream).await;
+            let header = frame.header();
+            frames.push((header.opcode, header.is_final, frame.into_data()));
+        }
+        assert_eq!(
+            frames,
+            [
+                (OpCode::Data(Data::Text), false, b"hell".to_vec()),
+                (OpCode::Data(Data::Continue), false, b"o wo".to_vec()),
+                (OpCode::Data(Data::Continue), true, b"rld".to_vec()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn reassembles_fragmented_messages() {
+        let addr = serve().await;
+        let (mut socket, _) = connect_async(format!("ws://{}/ws/echo?fragment=3", addr))
+            .await
+            .unwrap();
+
+        for frame in [
+            Frame::message(b"hel".to_vec(), OpCode::Data(Data::Text), false),
+            Frame::message(b"lo".to_vec(), OpCode::Data(Data::Continue), true),
+        ] {
+            socket.feed(ClientMessage::Frame(frame)).await.unwrap();
+        }
+        socket.flush().await.unwrap();
+        assert_eq!(
+            socket.next().await.unwrap().unwrap(),
+            ClientMessage::text("hello")
+        );
+    }
+
+    #[tokio::test]
+    async fn never_negotiates_extensions() {
+        let addr = serve().await;
+        let mut request = format!("ws://{}/ws/echo", addr)
+            .into_client_request()
+            .unwrap();
+        request.headers_mut().insert(
+            header::SEC_WEBSOCKET_EXTENSIONS,
+            "permessage-deflate".parse().unwrap(),
+        );
+        let (_, response) = connect_async(request).await.unwrap();
+
+        assert!(!response
+            .headers()
+            .contains_key(header::SEC_WEBSOCKET_EXTENSIONS));
+    }
+
+    async fn next_json(
+        socket: &mut tokio_tungstenite::WebSocketStream<
+            tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>,
+        >,
+    ) -> serde_json::Value {
+        match socket.next().await.unwrap().unwrap() {
+            ClientMessage::Text(text) => serde_json::from_str(&text).unwrap(),
+            message => panic!("expected a text message, got {:?}", message),
+        }
+    }
+
+    async fn room_counts(addr: SocketAddr) -> serde_json::Value {
+        let response = hyper::Client::new()
+            .get(format!("http://{}/ws/rooms", addr).parse().unwrap())
+            .await
+            .unwrap();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn relays_messages_and_presence_within_a_room() {
+        let addr = serve().await;
+        let url = format!("ws://{}/ws/room/lobby", addr);
+
+        let (mut alice, _) = connect_async(&url).await.unwrap();
+        assert_eq!(
+            next_json(&mut alice).await,
+            json!({ "event": "welcome", "member": 1, "members": 1 })
+        );
+        let (mut bob, _) = connect_async(&url).await.unwrap();
+        assert_eq!(
+            next_json(&mut bob).await,
+            json!({ "event": "welcome", "member": 2, "members": 2 })
+        );
+        assert_eq!(
+            next_json(&mut alice).await,
+            json!({ "event": "join", "member": 2, "members": 2 })
+        );
+        assert_eq!(room_counts(addr).await, json!({ "lobby": 2 }));
+
+        alice.send(ClientMessage::text("hi")).await.unwrap();
+        assert_eq!(
+            bob.next().await.unwrap().unwrap(),
+            ClientMessage::text("hi")
+        );
+        bob.send(ClientMessage::binary(vec![1, 2])).await.unwrap();
+        // Alice's next message is Bob's, so her own was not sent back to her.
+        assert_eq!(
+            alice.next().await.unwrap().unwrap(),
+            ClientMessage::binary(vec![1, 2])
+        );
+
+        bob.close(None).await.unwrap();
+        assert_eq!(
+            next_json(&mut alice).await,
+            json!({ "event": "leave", "member": 2, "members": 1 })
+        );
+        assert_eq!(room_counts(addr).await, json!({ "lobby": 1 }));
+    }
+
+    #[tokio::test]
+    async fn rooms_are_separate() {
+        let addr = serve().await;
+        let (mut first, _) = connect_async(format!("ws://{}/ws/room/a", addr))
+            .await
+            .unwrap();
+        let (mut second, _) = connect_async(format!("ws://{}/ws/room/b", addr))
+            .await
+            .unwrap();
+        next_json(&mut first).await;
+        assert_eq!(next_json(&mut second).await["members"], 1);
+        assert_eq!(room_counts(addr).await, json!({ "a": 1, "b": 1 }));
+
+        first.close(None).await.unwrap();
+        while first.next().await.is_some() {}
+        let counts = loop {
+            let counts = room_counts(addr).await;
+            if counts.get("a").is_none() {
+                break counts;
+            }
+            tokio::task::yield_now().await;
+        };
+        assert_eq!(counts, json!({ "b": 1 }));
+    }
+}
@@ -1,4 +1,8 @@
-use crate::routes::{request_inspection, response_formats, root, status_codes};
+use crate::compression::compress_response;
+use crate::routes::{
+    anything, dynamic_data, negotiate, request_inspection, response_formats, root, site, sse,
+    status_codes, trailers, websocket,
+};
 use axum::{
     http::{header, HeaderValue, Method, Request, StatusCode},
     middleware::{from_fn, Next},
@@ -10,11 +14,19 @@ use std::env;
 pub fn app() -> Router {
     Router::new()
         .merge(root::routes())
+        .merge(anything::routes())
+        .merge(dynamic_data::routes())
+        .merge(negotiate::routes())
         .merge(request_inspection::routes())
         .merge(response_formats::routes())
+        .merge(site::routes())
+        .merge(sse::routes())
         .merge(status_codes::routes())
+        .merge(trailers::routes())
+        .merge(websocket::routes())
         .layer(from_fn(inject_server_header))
         .layer(from_fn(inject_cors_headers))
+        .layer(from_fn(compress_response))
 }
 
 async fn inject_server_header<B>(request: Request<B>, next: Next<B>) -> Response {
@@ -71,11 +83,13 @@ async fn inject_cors_headers<B>(request: Request<B>, next: Next<B>) -> Response
 #[cfg(test)]
 mod tests {
     use super::*;
+    use async_compression::tokio::bufread::GzipDecoder;
     use axum::{
         body::Body,
         http::{header, HeaderValue, Request},
     };
-    use tower::ServiceExt;
+    use tokio::io::AsyncReadExt;
+    use tower::{Service, ServiceExt};
 
     #[tokio::test]
     async fn inserts_server_header() {
@@ -160,4 +174,142 @@ mod tests {
         assert!(headers.get(header::ACCESS_CONTROL_ALLOW_METHODS).is_some());
         assert!(headers.get(header::ACCESS_CONTROL_MAX_AGE).is_some());
     }
+
+    #[tokio::test]
+    async fn compresses_with_negotiated_encoding() {
+        let app = app();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/openapi.yaml")
+                    .header(header::ACCEPT_ENCODING, "deflate;q=0.5, gzip;q=0.9, br;q=0")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let headers = response.headers();
+        assert_eq!(
+            headers.get(header::CONTENT_ENCODING),
+            Some(&HeaderValue::from_static("gzip"))
+        );
+        assert_eq!(
+            headers.get(header::VARY),
+            Some(&HeaderValue::from_static("accept-encoding"))
+        );
+        assert!(headers.get(header::CONTENT_LENGTH).is_none());
+        assert!(headers[header::ETAG].as_bytes().starts_with(b"W/"));
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let mut decoded = String::new();
+        GzipDecoder::new(&body[..])
+            .read_to_string(&mut decoded)
+            .await
+            .unwrap();
+        assert!(decoded.starts_with("openapi: 3.0.3"));
+    }
+
+    #[tokio::test]
+    async fn varies_without_compressing_when_not_accepted() {
+        let app = app();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/openapi.yaml")
+                    .header(header::ACCEPT_ENCODING, "gzip")
+                    .header(header::CACHE_CONTROL, "no-transform")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let headers = response.headers();
+        assert!(headers.get(header::CONTENT_ENCODING).is_none());
+        assert_eq!(
+            headers.get(header::VARY),
+            Some(&HeaderValue::from_static("accept-encoding"))
+        );
+    }
+
+    #[tokio::test]
+    async fn does_not_compress_small_responses() {
+        let app = app();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/user-agent")
+                    .header(header::USER_AGENT, "x")
+                    .header(header::ACCEPT_ENCODING, "gzip")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let headers = response.headers();
+        assert!(headers.get(header::CONTENT_ENCODING).is_none());
+        // Only the `Accept` negotiation of the inspection endpoint is reflected.
+        assert_eq!(
+            headers.get_all(header::VARY).iter().collect::<Vec<_>>(),
+            [&HeaderValue::from_static("accept")]
+        );
+    }
+
+    #[tokio::test]
+    async fn head_and_not_modified_responses_have_no_body() {
+        let mut app = app();
+
+        let response = app
+            .ready()
+            .await
+            .unwrap()
+            .call(
+                Request::builder()
+                    .method(Method::HEAD)
+                    .uri("/openapi.yaml")
+                    .header(header::ACCEPT_ENCODING, "br")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            response.headers().get(header::CONTENT_ENCODING),
+            Some(&HeaderValue::from_static("br"))
+        );
+        let etag = response.headers()[header::ETAG].clone();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert!(body.is_empty());
+
+        let response = app
+            .ready()
+            .await
+            .unwrap()
+            .call(
+                Request::builder()
+                    .uri("/openapi.yaml")
+                    .header(header::ACCEPT_ENCODING, "br")
+                    .header(header::IF_NONE_MATCH, etag)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert!(response.headers().get(header::CONTENT_ENCODING).is_none());
+        assert_eq!(
+            response.headers().get(header::VARY),
+            Some(&HeaderValue::from_static("accept-encoding"))
+        );
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert!(body.is_empty());
+    }
 }